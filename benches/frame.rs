@@ -0,0 +1,88 @@
+//! Frame cost of the OpenGL backend for a 200x60 console, run with `cargo bench`
+//! on a machine with a display
+
+use glium::glutin;
+use roguelib::{create_window, Colour, Console, FixedSize, GliumBackend, Roguelib, Scaling};
+use std::time::{Duration, Instant};
+
+const WIDTH: u32 = 200;
+const HEIGHT: u32 = 60;
+const FRAMES: u32 = 500;
+
+/// Small deterministic generator so every run draws the same frames
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 33) as u32
+    }
+}
+
+/// Draw frames where a fraction of the cells changes every frame, returns the mean frame time
+fn bench(
+    roguelib: &mut Roguelib<GliumBackend>,
+    console: &mut Console,
+    changed_per_frame: u32,
+) -> roguelib::Result<Duration> {
+    let glyphs: Vec<char> = ('!'..='~').collect();
+    let mut rng = Lcg(42);
+
+    let start = Instant::now();
+    for _ in 0..FRAMES {
+        for i in 0..changed_per_frame {
+            // A full screen changes every cell, otherwise random cells change
+            let (x, y) = if changed_per_frame == WIDTH * HEIGHT {
+                (i % WIDTH, i / WIDTH)
+            } else {
+                (rng.next() % WIDTH, rng.next() % HEIGHT)
+            };
+            let glyph = glyphs[rng.next() as usize % glyphs.len()];
+            let fg = Colour::from_rgb8(rng.next() as u8, rng.next() as u8, rng.next() as u8);
+            console.set(x, y, glyph, fg, Colour::BLACK);
+        }
+        roguelib.draw("font", console)?;
+        roguelib.poll_events(|_| {})?;
+    }
+    Ok(start.elapsed() / FRAMES)
+}
+
+fn main() -> roguelib::Result<()> {
+    // Without vsync the frame time is not capped by the refresh rate
+    let backend = GliumBackend::with_context(
+        create_window("roguelib frame benchmark"),
+        glutin::ContextBuilder::new().with_vsync(false),
+    )?;
+    let mut roguelib = Roguelib::with_backend(backend);
+    roguelib.add_font("font", include_bytes!("../fonts/consola.ttf"), 12.0)?;
+    roguelib.set_fixed_size(Some(
+        FixedSize::new(WIDTH, HEIGHT).with_scaling(Scaling::Fractional),
+    ));
+    roguelib.set_grid_overlay(None);
+
+    let mut console = roguelib.create_console("font")?;
+    // Warm up the glyph cache and the buffers
+    bench(&mut roguelib, &mut console, WIDTH * HEIGHT)?;
+
+    for &(name, changed) in &[
+        ("every cell changes", WIDTH * HEIGHT),
+        ("10% of the cells change", WIDTH * HEIGHT / 10),
+        ("1% of the cells change", WIDTH * HEIGHT / 100),
+        ("nothing changes", 0),
+    ] {
+        let frame = bench(&mut roguelib, &mut console, changed)?;
+        println!(
+            "{:>24}: {:>8.3} ms per frame",
+            name,
+            frame.as_secs_f64() * 1000.0
+        );
+    }
+
+    if let Some(stats) = roguelib.backend().font("font")?.atlas_stats() {
+        println!("{:?}", stats);
+    }
+    Ok(())
+}
@@ -0,0 +1,567 @@
+use crate::error::{Result, RoguelibError};
+use rusttype::gpu_cache::{Cache, CachedBy};
+use rusttype::{PositionedGlyph, Rect};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Size and precision of the texture that the glyphs of a TrueType font are rasterized into.
+/// Sizes below a pixel are raised to a pixel and tolerances below 0 are raised to 0
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AtlasConfig {
+    /// Width of the texture when the font is added
+    pub width: u32,
+    /// Height of the texture when the font is added
+    pub height: u32,
+    /// Largest width the texture grows to when the glyphs of a frame do not fit
+    pub max_width: u32,
+    /// Largest height the texture grows to when the glyphs of a frame do not fit
+    pub max_height: u32,
+    /// Glyphs with a scale within this tolerance share their rasterized glyph
+    pub scale_tolerance: f32,
+    /// Glyphs with a subpixel position within this many pixels share their rasterized glyph
+    pub position_tolerance: f32,
+}
+
+impl Default for AtlasConfig {
+    fn default() -> AtlasConfig {
+        AtlasConfig {
+            width: 512,
+            height: 512,
+            max_width: 4096,
+            max_height: 4096,
+            scale_tolerance: 0.1,
+            position_tolerance: 0.1,
+        }
+    }
+}
+
+impl AtlasConfig {
+    /// The config with sizes and tolerances the atlas can use, the cache panics on negative
+    /// tolerances and an atlas without pixels never grows large enough for a glyph
+    fn clamped(self) -> AtlasConfig {
+        // Also replaces tolerances that are not a number
+        let tolerance = |t: f32| if t >= 0.0 { t } else { 0.0 };
+        AtlasConfig {
+            width: self.width.max(1),
+            height: self.height.max(1),
+            max_width: self.max_width.max(1),
+            max_height: self.max_height.max(1),
+            scale_tolerance: tolerance(self.scale_tolerance),
+            position_tolerance: tolerance(self.position_tolerance),
+        }
+    }
+}
+
+/// Usage of the glyph atlas of a font, counted since the font was added
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct AtlasStats {
+    /// Glyphs that were drawn from the atlas without rasterizing them
+    pub hits: u64,
+    /// Glyphs that had to be rasterized into the atlas
+    pub misses: u64,
+    /// Rows of glyphs that were dropped from the full atlas to make room for other glyphs
+    pub evictions: u64,
+    /// Times the atlas texture doubled in size
+    pub growths: u64,
+    /// Width of the atlas texture in pixels
+    pub width: u32,
+    /// Height of the atlas texture in pixels
+    pub height: u32,
+    /// Part of the atlas that is covered by glyphs, between 0 and 1
+    pub fill: f32,
+}
+
+/// Texture and cache that glyphs are rasterized into when they are first drawn
+pub(crate) struct GlyphAtlas {
+    glyphs: GlyphCache,
+    texture: glium::texture::Texture2d,
+}
+
+impl GlyphAtlas {
+    /// Create an empty atlas with the initial size of the config
+    pub(crate) fn new(display: &glium::Display, config: AtlasConfig) -> Result<GlyphAtlas> {
+        let glyphs = GlyphCache::new(config);
+        let (width, height) = glyphs.cache.dimensions();
+        Ok(GlyphAtlas {
+            glyphs,
+            texture: create_texture(display, width, height)?,
+        })
+    }
+
+    /// Queue a glyph to be rasterized by the next call to `cache_queued`
+    pub(crate) fn queue(&mut self, glyph: PositionedGlyph<'static>) {
+        self.glyphs.queue(glyph);
+    }
+
+    /// Rasterize the queued glyphs that are not in the atlas yet, the atlas grows when the
+    /// queue does not fit. Returns whether queued glyphs that were cached before have moved,
+    /// glyphs that were not queued may be dropped to make room either way
+    pub(crate) fn cache_queued(&mut self, display: &glium::Display) -> Result<bool> {
+        let mut moved = false;
+        loop {
+            let texture = &self.texture;
+            let cached = self.glyphs.cache_queued(|rect, data| {
+                texture.main_level().write(
+                    glium::Rect {
+                        left: rect.min.x,
+                        bottom: rect.min.y,
+                        width: rect.width(),
+                        height: rect.height(),
+                    },
+                    glium::texture::RawImage2d {
+                        data: Cow::Borrowed(data),
+                        width: rect.width(),
+                        height: rect.height(),
+                        format: glium::texture::ClientFormat::U8,
+                    },
+                );
+            });
+            match cached {
+                Some(reordered) => return Ok(moved || reordered),
+                None => {
+                    let (width, height) = self.glyphs.grow()?;
+                    self.texture = create_texture(display, width, height)?;
+                    moved = true;
+                }
+            }
+        }
+    }
+
+    /// Texture coordinates and pixel rectangle of a cached glyph
+    pub(crate) fn rect_for(&self, glyph: &PositionedGlyph) -> Option<(Rect<f32>, Rect<i32>)> {
+        self.glyphs
+            .cache
+            .rect_for(0, glyph)
+            .ok()
+            .and_then(|rect| rect)
+    }
+
+    /// Texture that contains the rasterized glyphs
+    pub(crate) fn texture(&self) -> &glium::texture::Texture2d {
+        &self.texture
+    }
+
+    /// Usage of the atlas since it was created
+    pub(crate) fn stats(&self) -> AtlasStats {
+        self.glyphs.usage.stats()
+    }
+}
+
+/// The cache of an atlas with its usage, the texture is written by the caller
+struct GlyphCache {
+    cache: Cache<'static>,
+    config: AtlasConfig,
+    usage: AtlasUsage<PositionedGlyph<'static>>,
+    /// Queued glyphs that were not cached when they were queued, the rows they are uploaded
+    /// into are recognized by them
+    uncached: Vec<PositionedGlyph<'static>>,
+    /// Whether rows lack a glyph, rows packed again after a growth only contain glyphs that
+    /// were cached before, so they are recognized by the glyphs queued later
+    unsampled: bool,
+}
+
+impl GlyphCache {
+    /// Create an empty cache with the initial size of the config
+    fn new(config: AtlasConfig) -> GlyphCache {
+        let config = config.clamped();
+        let cache = Cache::builder()
+            .dimensions(config.width, config.height)
+            .scale_tolerance(config.scale_tolerance)
+            .position_tolerance(config.position_tolerance)
+            .build();
+
+        GlyphCache {
+            cache,
+            config,
+            usage: AtlasUsage::new(config.width, config.height),
+            uncached: Vec::new(),
+            unsampled: false,
+        }
+    }
+
+    /// Queue a glyph to be rasterized by the next call to `cache_queued`
+    fn queue(&mut self, glyph: PositionedGlyph<'static>) {
+        // Glyphs without pixels, like spaces, are never cached
+        if glyph.pixel_bounding_box().is_some() {
+            match texture_row(&self.cache, &glyph) {
+                None => self.uncached.push(glyph.clone()),
+                Some(top) if self.unsampled => self.usage.sample(top, glyph.clone()),
+                Some(_) => {}
+            }
+            self.usage.queued();
+            self.cache.queue_glyph(0, glyph);
+        }
+    }
+
+    /// Rasterize the queued glyphs that are not cached yet and upload them, returns whether
+    /// the cache was packed again or nothing when the queue does not fit
+    fn cache_queued<F: FnMut(Rect<u32>, &[u8])>(&mut self, mut upload: F) -> Option<bool> {
+        let mut uploads = Vec::new();
+        let cached = self.cache.cache_queued(|rect, data| {
+            upload(rect, data);
+            uploads.push(rect);
+        });
+        let reordered = match cached {
+            Ok(CachedBy::Adding) => false,
+            // Every row was dropped and the queued glyphs were packed again
+            Ok(CachedBy::Reordering) => true,
+            Err(_) => return None,
+        };
+
+        self.usage.cached(&mut uploads, reordered);
+        // The rows that were dropped to make room are no longer where their glyphs are
+        let cache = &self.cache;
+        let row_of = |glyph: &PositionedGlyph| texture_row(cache, glyph);
+        for glyph in self.uncached.drain(..) {
+            if let Some(top) = row_of(&glyph) {
+                self.usage.sample(top, glyph);
+            }
+        }
+        self.usage.retain(row_of);
+        self.unsampled = self.usage.unsampled();
+        Some(reordered)
+    }
+
+    /// Double the size of the cache, dropping every cached glyph, returns the new size. A cache
+    /// that can not grow drops the queue as well, so the next frame starts with an empty queue
+    fn grow(&mut self) -> Result<(u32, u32)> {
+        let (width, height) = self.cache.dimensions();
+        if width >= self.config.max_width && height >= self.config.max_height {
+            // The cache only clears its queue after a successful pass
+            self.cache.clear_queue();
+            self.uncached.clear();
+            self.usage.dropped_queue();
+            return Err(RoguelibError::CacheOverflow);
+        }
+        let width = (width * 2).min(self.config.max_width.max(width));
+        let height = (height * 2).min(self.config.max_height.max(height));
+
+        self.cache
+            .to_builder()
+            .dimensions(width, height)
+            .rebuild(&mut self.cache);
+        self.usage.grown(width, height);
+        Ok((width, height))
+    }
+}
+
+/// Pixel row of the texture where a glyph is cached, nothing when it is not cached
+fn texture_row(cache: &Cache, glyph: &PositionedGlyph) -> Option<u32> {
+    let (uv_rect, _) = cache.rect_for(0, glyph).ok()??;
+    let (_, height) = cache.dimensions();
+    Some((uv_rect.min.y * height as f32).round() as u32)
+}
+
+/// Counts the hits and misses of an atlas and follows the rows the cache packs its glyphs in,
+/// without touching the texture. The cache starts a row with a glyph at the left edge and
+/// drops its least recently used rows when a new row does not fit, anywhere in the texture.
+/// A glyph of every row tells whether the row is still there
+struct AtlasUsage<G> {
+    stats: AtlasStats,
+    /// Glyphs queued since the last cache pass
+    queued: u64,
+    /// Rows of glyphs in the atlas by the top of the row
+    rows: HashMap<u32, Row<G>>,
+}
+
+/// A row of glyphs in the atlas
+struct Row<G> {
+    height: u32,
+    /// Number of pixels covered by the glyphs of the row
+    area: u64,
+    /// A glyph that was uploaded into the row
+    sample: Option<G>,
+}
+
+impl<G> AtlasUsage<G> {
+    /// Usage of an empty atlas of a size
+    fn new(width: u32, height: u32) -> AtlasUsage<G> {
+        AtlasUsage {
+            stats: AtlasStats {
+                width,
+                height,
+                ..Default::default()
+            },
+            queued: 0,
+            rows: HashMap::new(),
+        }
+    }
+
+    /// A glyph was queued for the next cache pass
+    fn queued(&mut self) {
+        self.queued += 1;
+    }
+
+    /// The queued glyphs were dropped without a successful cache pass
+    fn dropped_queue(&mut self) {
+        self.queued = 0;
+    }
+
+    /// A cache pass succeeded with these uploads, queued glyphs that were not uploaded were
+    /// hits. A reordered cache dropped every row and packed the queued glyphs again
+    fn cached(&mut self, uploads: &mut [Rect<u32>], reordered: bool) {
+        let uploaded = uploads.len() as u64;
+        self.stats.misses += uploaded;
+        self.stats.hits += self.queued.saturating_sub(uploaded);
+        self.queued = 0;
+        if reordered {
+            self.stats.evictions += self.rows.len() as u64;
+            self.rows.clear();
+        }
+
+        // Glyphs may be uploaded in any order, the rows are started first
+        uploads.sort_by_key(|rect| rect.min.x);
+        for rect in uploads.iter() {
+            let (top, height) = (rect.min.y, rect.height());
+            // Rows the new row overlaps were dropped to make room for it
+            if rect.min.x == 0 {
+                let rows = self.rows.len();
+                self.rows
+                    .retain(|&row_top, row| row_top + row.height <= top || row_top >= top + height);
+                self.stats.evictions += (rows - self.rows.len()) as u64;
+                self.rows.insert(
+                    top,
+                    Row {
+                        height,
+                        area: 0,
+                        sample: None,
+                    },
+                );
+            }
+            if let Some(row) = self.rows.get_mut(&top) {
+                row.area += u64::from(rect.width()) * u64::from(height);
+            }
+        }
+    }
+
+    /// A glyph cached at a pixel row of the texture, the first glyph seen in a row tells
+    /// whether the row is still there
+    fn sample(&mut self, y: u32, glyph: G) {
+        let row = self
+            .rows
+            .iter_mut()
+            .find(|(&top, row)| y >= top && y < top + row.height);
+        if let Some((_, row)) = row {
+            if row.sample.is_none() {
+                row.sample = Some(glyph);
+            }
+        }
+    }
+
+    /// Whether there are rows without a glyph
+    fn unsampled(&self) -> bool {
+        self.rows.values().any(|row| row.sample.is_none())
+    }
+
+    /// Drop the rows whose glyph is no longer cached inside of the row, `row_of` gives the
+    /// pixel row a glyph is cached at
+    fn retain<F: Fn(&G) -> Option<u32>>(&mut self, row_of: F) {
+        let rows = self.rows.len();
+        self.rows.retain(|&top, row| match &row.sample {
+            Some(glyph) => row_of(glyph).is_some_and(|y| y >= top && y < top + row.height),
+            None => true,
+        });
+        self.stats.evictions += (rows - self.rows.len()) as u64;
+    }
+
+    /// The atlas grew to a new size, dropping every row
+    fn grown(&mut self, width: u32, height: u32) {
+        self.rows.clear();
+        self.stats.growths += 1;
+        self.stats.width = width;
+        self.stats.height = height;
+    }
+
+    /// Usage with the fill of the rows that are in the atlas
+    fn stats(&self) -> AtlasStats {
+        let size = u64::from(self.stats.width) * u64::from(self.stats.height);
+        let used: u64 = self.rows.values().map(|row| row.area).sum();
+        AtlasStats {
+            fill: (used as f64 / size as f64).min(1.0) as f32,
+            ..self.stats
+        }
+    }
+}
+
+/// Create a cleared single channel texture for the atlas
+fn create_texture(
+    display: &glium::Display,
+    width: u32,
+    height: u32,
+) -> Result<glium::texture::Texture2d> {
+    glium::texture::Texture2d::with_format(
+        display,
+        glium::texture::RawImage2d {
+            data: Cow::Owned(vec![0u8; width as usize * height as usize]),
+            width,
+            height,
+            format: glium::texture::ClientFormat::U8,
+        },
+        glium::texture::UncompressedFloatFormat::U8,
+        glium::texture::MipmapsOption::NoMipmap,
+    )
+    .map_err(RoguelibError::context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configs_are_clamped() {
+        let config = AtlasConfig {
+            width: 0,
+            height: 0,
+            max_width: 0,
+            max_height: 8,
+            scale_tolerance: -1.0,
+            position_tolerance: f32::NAN,
+        };
+        assert_eq!(
+            config.clamped(),
+            AtlasConfig {
+                width: 1,
+                height: 1,
+                max_width: 1,
+                max_height: 8,
+                scale_tolerance: 0.0,
+                position_tolerance: 0.0,
+            }
+        );
+        assert_eq!(AtlasConfig::default().clamped(), AtlasConfig::default());
+    }
+
+    /// Rectangle of an uploaded glyph
+    fn upload(x: u32, y: u32, width: u32, height: u32) -> Rect<u32> {
+        Rect {
+            min: rusttype::point(x, y),
+            max: rusttype::point(x + width, y + height),
+        }
+    }
+
+    #[test]
+    fn hits_and_misses_are_counted_per_pass() {
+        let mut usage = AtlasUsage::<u32>::new(100, 100);
+        for _ in 0..5 {
+            usage.queued();
+        }
+        assert_eq!(usage.stats().hits, 0);
+        usage.cached(&mut [upload(0, 0, 10, 10), upload(10, 0, 10, 10)], false);
+        let stats = usage.stats();
+        assert_eq!((stats.hits, stats.misses), (3, 2));
+
+        usage.queued();
+        usage.cached(&mut [], false);
+        let stats = usage.stats();
+        assert_eq!((stats.hits, stats.misses), (4, 2));
+    }
+
+    #[test]
+    fn fill_counts_the_rows_in_the_atlas() {
+        let mut usage = AtlasUsage::<u32>::new(100, 100);
+        // The second glyph of the row is uploaded before the glyph that starts it
+        usage.cached(&mut [upload(20, 0, 30, 10), upload(0, 0, 20, 10)], false);
+        usage.cached(&mut [upload(0, 10, 50, 20)], false);
+        assert_eq!(usage.stats().fill, 0.15);
+        assert_eq!(usage.stats().evictions, 0);
+    }
+
+    #[test]
+    fn overlapped_and_dropped_rows_are_evictions() {
+        let mut usage = AtlasUsage::new(100, 100);
+        usage.cached(
+            &mut [
+                upload(0, 0, 100, 10),
+                upload(0, 10, 100, 10),
+                upload(0, 20, 100, 10),
+                upload(0, 30, 100, 10),
+            ],
+            false,
+        );
+        for (glyph, y) in &[(0, 1), (1, 11), (2, 21), (3, 31)] {
+            usage.sample(*y, *glyph);
+        }
+        assert!(!usage.unsampled());
+        assert_eq!(usage.stats().fill, 0.4);
+
+        // A taller row replaces the two rows at the top
+        usage.cached(&mut [upload(0, 0, 50, 20)], false);
+        assert_eq!(usage.stats().evictions, 2);
+        assert!(usage.unsampled());
+        usage.sample(1, 4);
+
+        // The last row was dropped as well while it does not touch the new row,
+        // the third row is still where it was
+        let cached: HashMap<u32, u32> = [(2, 21), (4, 1)].iter().cloned().collect();
+        usage.retain(|glyph| cached.get(glyph).cloned());
+        let stats = usage.stats();
+        assert_eq!(stats.evictions, 3);
+        assert_eq!(stats.fill, 0.2);
+
+        // Every row is dropped when the queue is packed again
+        usage.cached(&mut [upload(0, 0, 10, 10)], true);
+        let stats = usage.stats();
+        assert_eq!(stats.evictions, 5);
+        assert_eq!(stats.fill, 0.01);
+    }
+
+    #[test]
+    fn overflowing_queues_are_dropped() {
+        let font = rusttype::Font::from_bytes(&include_bytes!("../fonts/consola.ttf")[..]).unwrap();
+        let mut glyphs = GlyphCache::new(AtlasConfig {
+            width: 32,
+            height: 32,
+            max_width: 64,
+            max_height: 64,
+            ..AtlasConfig::default()
+        });
+        // Every printable character does not fit in the largest atlas
+        let frame = |glyphs: &mut GlyphCache| {
+            for c in '!'..='~' {
+                let glyph = font
+                    .glyph(c)
+                    .scaled(rusttype::Scale::uniform(16.0))
+                    .positioned(rusttype::point(0.0, 0.0));
+                glyphs.queue(glyph);
+            }
+            let queued = glyphs.uncached.len();
+            while glyphs.cache_queued(|_, _| {}).is_none() {
+                if let Err(e) = glyphs.grow() {
+                    return (queued, e);
+                }
+            }
+            panic!("the glyphs fit in the atlas");
+        };
+
+        // Glyphs packed by a failed pass may stay cached, the glyphs of earlier frames do not
+        // pile up
+        let mut last = 94;
+        for _ in 0..2 {
+            let (queued, error) = frame(&mut glyphs);
+            assert!(queued <= last);
+            last = queued;
+            assert!(matches!(error, RoguelibError::CacheOverflow));
+            assert!(glyphs.uncached.is_empty());
+            assert_eq!(glyphs.usage.queued, 0);
+        }
+        let stats = glyphs.usage.stats();
+        assert_eq!((stats.hits, stats.misses, stats.growths), (0, 0, 1));
+    }
+
+    #[test]
+    fn growth_drops_the_rows_without_evicting() {
+        let mut usage = AtlasUsage::<u32>::new(100, 100);
+        usage.cached(&mut [upload(0, 0, 100, 50)], false);
+        usage.grown(200, 100);
+        usage.cached(&mut [upload(0, 0, 100, 50)], false);
+        let stats = usage.stats();
+        assert_eq!((stats.width, stats.height), (200, 100));
+        assert_eq!(stats.growths, 1);
+        assert_eq!(stats.evictions, 0);
+        assert_eq!(stats.fill, 0.25);
+        // Rows that were packed again are recognized by glyphs queued later
+        assert!(usage.unsampled());
+        usage.sample(10, 0);
+        assert!(!usage.unsampled());
+    }
+}
@@ -0,0 +1,472 @@
+use crate::backend::Backend;
+use crate::colour::Colour;
+use crate::console::Console;
+use crate::error::{Result, RoguelibError};
+use crate::input::{Event, MouseTracker};
+use crate::tileset::Tileset;
+use crate::util::{FixedSize, LineGrid};
+use rusttype::{Font, Scale, Vector};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// An rgba image in memory, stored row by row from the top left corner
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl Image {
+    /// Create an image filled with a single colour
+    pub fn new(width: u32, height: u32, colour: Colour) -> Image {
+        let pixel = to_rgba8(colour);
+        Image {
+            width,
+            height,
+            data: pixel
+                .iter()
+                .cloned()
+                .cycle()
+                .take(width as usize * height as usize * 4)
+                .collect(),
+        }
+    }
+
+    /// Width of the image in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the image in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Raw rgba data of the image
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Retrieve the rgba value of a single pixel
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        Some([
+            self.data[i],
+            self.data[i + 1],
+            self.data[i + 2],
+            self.data[i + 3],
+        ])
+    }
+
+    /// Write the image to a png file
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let io_error = |e| RoguelibError::Io(path.to_path_buf(), e);
+        let file = File::create(path).map_err(io_error)?;
+        image::png::PNGEncoder::new(io::BufWriter::new(file))
+            .encode(
+                &self.data,
+                self.width,
+                self.height,
+                image::ColorType::RGBA(8),
+            )
+            .map_err(io_error)
+    }
+
+    /// Read an image from a png file, images without alpha are opaque
+    pub fn load_png<P: AsRef<Path>>(path: P) -> Result<Image> {
+        let path = path.as_ref();
+        let io_error = |e| RoguelibError::Io(path.to_path_buf(), e);
+        let bytes = std::fs::read(path).map_err(io_error)?;
+        let image = image::load_from_memory_with_format(&bytes, image::ImageFormat::PNG)
+            .map_err(|e| io_error(io::Error::new(io::ErrorKind::InvalidData, e.to_string())))?
+            .to_rgba();
+        let (width, height) = image.dimensions();
+        Ok(Image {
+            width,
+            height,
+            data: image.into_raw(),
+        })
+    }
+
+    /// Number of pixels with a component that differs more than the tolerance from the same
+    /// pixel of the other image, every pixel differs when the sizes differ
+    pub fn diff(&self, other: &Image, tolerance: u8) -> usize {
+        if (self.width, self.height) != (other.width, other.height) {
+            return self.data.len().max(other.data.len()) / 4;
+        }
+        self.data
+            .chunks(4)
+            .zip(other.data.chunks(4))
+            .filter(|(a, b)| {
+                a.iter()
+                    .zip(b.iter())
+                    .any(|(a, b)| a.max(b) - a.min(b) > tolerance)
+            })
+            .count()
+    }
+
+    /// Alpha blend a colour over a single pixel, coverage scales the alpha of the colour
+    fn blend(&mut self, x: i32, y: i32, colour: Colour, coverage: f32) {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let alpha = colour.a * coverage;
+        let source = [colour.r, colour.g, colour.b, colour.a];
+        for (c, s) in self.data[i..i + 4].iter_mut().zip(source.iter()) {
+            let d = f32::from(*c) / 255.0;
+            *c = ((s * alpha + d * (1.0 - alpha)) * 255.0).round() as u8;
+        }
+    }
+}
+
+/// Convert a colour to 8 bit components
+fn to_rgba8(colour: Colour) -> [u8; 4] {
+    let c = |f: f32| (f.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [c(colour.r), c(colour.g), c(colour.b), c(colour.a)]
+}
+
+/// A font rasterized on the cpu
+enum HeadlessFont {
+    TrueType {
+        font: Font<'static>,
+        cell_dimensions: Vector<f32>,
+        scale: Scale,
+    },
+    Tileset {
+        tileset: Tileset,
+        cell_dimensions: Vector<f32>,
+    },
+}
+
+/// Backend that rasterizes the console on the cpu into an in-memory image,
+/// using the same grid as the glium backend so the output can be compared
+pub struct HeadlessBackend {
+    fonts: HashMap<String, HeadlessFont>,
+    width: u32,
+    height: u32,
+    image: Image,
+    events: VecDeque<Event>,
+    fixed_size: Option<FixedSize>,
+    /// Cell under the simulated mouse in the grid of the last presented frame
+    mouse: MouseTracker,
+    /// Whether waiting without queued events closes the backend instead of failing
+    close_when_idle: bool,
+}
+
+impl HeadlessBackend {
+    /// Create a backend for an image with the given physical dimensions
+    pub fn new(width: u32, height: u32) -> HeadlessBackend {
+        HeadlessBackend {
+            fonts: HashMap::new(),
+            width,
+            height,
+            image: Image::new(width, height, Colour::BLACK),
+            events: VecDeque::new(),
+            fixed_size: None,
+            mouse: MouseTracker::default(),
+            close_when_idle: false,
+        }
+    }
+
+    /// Deliver `Event::CloseRequested` when the backend waits for input after the queued
+    /// events have run out, instead of failing with `RoguelibError::NoEvents`. Useful to run
+    /// a turn based game until it has handled the simulated input
+    pub fn close_when_idle(mut self, close: bool) -> HeadlessBackend {
+        self.close_when_idle = close;
+        self
+    }
+
+    /// The image of the last presented frame
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Queue an event that is delivered on the next poll, used to simulate input
+    pub fn push_event(&mut self, event: Event) {
+        self.events.push_back(event);
+    }
+
+    /// Queue the events of the mouse moving to a position in physical pixels,
+    /// the cells are those of the last presented frame
+    pub fn push_mouse_moved(&mut self, x: f32, y: f32) {
+        let events = &mut self.events;
+        self.mouse
+            .moved(Vector { x, y }, &mut |event| events.push_back(event));
+    }
+
+    /// The line grid for the font
+    fn grid_for(&self, font: &HeadlessFont) -> LineGrid {
+        let grid = match font {
+            HeadlessFont::TrueType {
+                font,
+                cell_dimensions,
+                scale,
+            } => {
+                crate::util::font_line_grid(font, *scale, cell_dimensions, self.width, self.height)
+            }
+            HeadlessFont::Tileset {
+                cell_dimensions, ..
+            } => crate::util::tile_line_grid(cell_dimensions, self.width, self.height),
+        };
+        match self.fixed_size {
+            Some(size) => grid.fixed(&size),
+            None => grid,
+        }
+    }
+
+    /// The font with the given name
+    fn font(&self, name: &str) -> Result<&HeadlessFont> {
+        self.fonts
+            .get(name)
+            .ok_or_else(|| RoguelibError::MissingFont(name.into()))
+    }
+
+    /// Draw the console into a new image, cells that do not fit are skipped
+    pub fn render(&self, font: &str, console: &Console) -> Result<Image> {
+        let font = self.font(font)?;
+        let grid = self.grid_for(font);
+        let mut image = Image::new(self.width, self.height, Colour::BLACK);
+
+        // Fill the cell backgrounds
+        for (x, y, cell) in console.cells() {
+            if x >= grid.totals().x || y >= grid.totals().y {
+                continue;
+            }
+            let rect = grid.cell_rect(x, y);
+            for py in rect.min.y.round() as i32..rect.max.y.round() as i32 {
+                for px in rect.min.x.round() as i32..rect.max.x.round() as i32 {
+                    image.blend(px, py, cell.bg, 1.0);
+                }
+            }
+        }
+
+        // Rasterize the glyphs on top of the backgrounds
+        match font {
+            HeadlessFont::TrueType { font, scale, .. } => {
+                let glyphs = crate::util::layout_grid(font, *scale, &grid, console);
+                for (glyph, fg) in glyphs {
+                    if let Some(bounding_box) = glyph.pixel_bounding_box() {
+                        glyph.draw(|gx, gy, coverage| {
+                            image.blend(
+                                bounding_box.min.x + gx as i32,
+                                bounding_box.min.y + gy as i32,
+                                fg,
+                                coverage,
+                            );
+                        });
+                    }
+                }
+            }
+            HeadlessFont::Tileset { tileset, .. } => {
+                draw_tiles(&mut image, &grid, console, tileset);
+            }
+        }
+
+        Ok(image)
+    }
+}
+
+impl Backend for HeadlessBackend {
+    /// The scale is in physical pixels
+    fn add_font(&mut self, name: String, font: Font<'static>, scale: f32) -> Result<()> {
+        let scale = Scale::uniform(crate::util::check_scale(scale)?);
+        let cell_dimensions = crate::util::cell_dimensions(&font, scale)?;
+
+        self.fonts.insert(
+            name,
+            HeadlessFont::TrueType {
+                font,
+                cell_dimensions,
+                scale,
+            },
+        );
+        Ok(())
+    }
+
+    /// The scale multiplies the size of the tiles
+    fn add_tileset(&mut self, name: String, tileset: Tileset, scale: f32) -> Result<()> {
+        let scale = crate::util::check_scale(scale)?;
+        let cell_dimensions = Vector {
+            x: tileset.tile_width() as f32 * scale,
+            y: tileset.tile_height() as f32 * scale,
+        };
+        self.fonts.insert(
+            name,
+            HeadlessFont::Tileset {
+                tileset,
+                cell_dimensions,
+            },
+        );
+        Ok(())
+    }
+
+    fn line_grid(&self, font: &str) -> Result<LineGrid> {
+        Ok(self.grid_for(self.font(font)?))
+    }
+
+    fn present(&mut self, font: &str, console: &Console) -> Result<()> {
+        self.image = self.render(font, console)?;
+        self.mouse.set_grid(self.line_grid(font)?);
+        Ok(())
+    }
+
+    fn set_fixed_size(&mut self, size: Option<FixedSize>) {
+        self.fixed_size = size;
+    }
+
+    fn poll_events(&mut self, callback: &mut dyn FnMut(Event)) -> Result<()> {
+        while let Some(event) = self.events.pop_front() {
+            callback(event);
+        }
+        Ok(())
+    }
+
+    /// Nothing can push events while the backend waits, so waiting after the queued events
+    /// have run out fails with `RoguelibError::NoEvents`, or delivers `Event::CloseRequested`
+    /// when the backend closes when idle. `run` draws its first frame before it waits, so the
+    /// image has the frame of a game that received no input
+    fn wait_events(&mut self, callback: &mut dyn FnMut(Event)) -> Result<()> {
+        if self.events.is_empty() {
+            if !self.close_when_idle {
+                return Err(RoguelibError::NoEvents);
+            }
+            self.events.push_back(Event::CloseRequested);
+        }
+        self.poll_events(callback)
+    }
+}
+
+/// Draw the tile of every cell, scaling the tiles with nearest neighbour sampling
+fn draw_tiles(image: &mut Image, grid: &LineGrid, console: &Console, tileset: &Tileset) {
+    let (sheet_width, _) = tileset.dimensions();
+    let coverage = tileset.coverage();
+    for (x, y, cell) in console.cells() {
+        if x >= grid.totals().x || y >= grid.totals().y {
+            continue;
+        }
+        let tile = match tileset.tile_for(cell.glyph) {
+            Some(tile) => tile,
+            None => continue,
+        };
+        let (tile_x, tile_y) = tileset.tile_origin(tile);
+        let rect = grid.cell_rect(x, y);
+        let (min_x, min_y) = (rect.min.x.round() as i32, rect.min.y.round() as i32);
+        let (max_x, max_y) = (rect.max.x.round() as i32, rect.max.y.round() as i32);
+
+        for py in min_y..max_y {
+            let sy =
+                tile_y + ((py - min_y) as u32 * tileset.tile_height()) / (max_y - min_y) as u32;
+            for px in min_x..max_x {
+                let sx =
+                    tile_x + ((px - min_x) as u32 * tileset.tile_width()) / (max_x - min_x) as u32;
+                let value = coverage[(sy * sheet_width + sx) as usize];
+                image.blend(px, py, cell.fg, f32::from(value) / 255.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::Cell;
+
+    /// Backend with the regular consola font at 20 pixels
+    fn backend() -> HeadlessBackend {
+        let mut backend = HeadlessBackend::new(200, 100);
+        let font = Font::from_bytes(&include_bytes!("../../fonts/consola.ttf")[..]).unwrap();
+        backend.add_font("default".into(), font, 20.0).unwrap();
+        backend
+    }
+
+    #[test]
+    fn renders_backgrounds_and_glyphs() {
+        let mut backend = backend();
+        let mut console = Console::new(2, 1);
+        console.set(0, 0, ' ', Colour::WHITE, Colour::BLUE);
+        console.set(1, 0, '█', Colour::RED, Colour::BLACK);
+        backend.present("default", &console).unwrap();
+
+        let grid = backend.line_grid("default").unwrap();
+        let centre = |x, y| {
+            let rect = grid.cell_rect(x, y);
+            let (x, y) = (
+                (rect.min.x + rect.max.x) / 2.0,
+                (rect.min.y + rect.max.y) / 2.0,
+            );
+            backend.image().pixel(x as u32, y as u32).unwrap()
+        };
+        assert_eq!(centre(0, 0), [0, 0, 255, 255]);
+        assert_eq!(centre(1, 0), [255, 0, 0, 255]);
+        // Cells that are not part of the console stay black
+        assert_eq!(centre(2, 0), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn png_round_trip_and_diff() {
+        let mut image = Image::new(3, 2, Colour::BLUE);
+        image.blend(1, 1, Colour::RED, 0.5);
+        let path = std::env::temp_dir().join(format!("roguelib-{}.png", std::process::id()));
+        image.save_png(&path).unwrap();
+        let loaded = Image::load_png(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, image);
+
+        let mut changed = image.clone();
+        changed.blend(0, 0, Colour::WHITE, 0.01);
+        assert_eq!(image.diff(&changed, 0), 1);
+        assert_eq!(image.diff(&changed, 3), 0);
+        assert_eq!(image.diff(&Image::new(2, 3, Colour::BLUE), 255), 6);
+        assert!(Image::load_png("fonts/consola.ttf").is_err());
+    }
+
+    /// Compare an image with a png in the golden directory, `ROGUELIB_BLESS=1` writes the
+    /// image as the new golden image instead
+    fn assert_golden(image: &Image, name: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(name);
+        if std::env::var_os("ROGUELIB_BLESS").is_some() {
+            image.save_png(&path).unwrap();
+        }
+        let golden = Image::load_png(&path).unwrap();
+        // Rasterizers may round a little differently between platforms
+        let differing = image.diff(&golden, 2);
+        if differing > 0 {
+            let actual = std::env::temp_dir().join(name);
+            image.save_png(&actual).unwrap();
+            panic!(
+                "{} pixels differ from {}, the image is written to {}",
+                differing,
+                path.display(),
+                actual.display()
+            );
+        }
+    }
+
+    #[test]
+    fn golden_text_and_backgrounds() {
+        let mut backend = backend();
+        let mut console = Console::new(20, 5);
+        console.fill(Cell {
+            bg: Colour::from_rgb8(20, 20, 40),
+            ..Default::default()
+        });
+        console.print(1, 1, "Hello, @ world!");
+        for (x, c) in "#####".chars().enumerate() {
+            console.set(x as u32 + 1, 3, c, Colour::YELLOW, Colour::DARK_GREY);
+        }
+        console.set(8, 3, '█', Colour::RED, Colour::BLUE);
+        console.set(10, 3, 'g', Colour::GREEN, Colour::WHITE);
+        backend.present("default", &console).unwrap();
+        assert_golden(backend.image(), "text_and_backgrounds.png");
+    }
+}
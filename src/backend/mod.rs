@@ -0,0 +1,77 @@
+use crate::console::Console;
+use crate::error::Result;
+use crate::input::Event;
+use crate::tileset::Tileset;
+use crate::util::{FixedSize, GridOverlay, LineGrid};
+use rusttype::Font;
+use std::time::Duration;
+
+mod headless;
+#[cfg(feature = "opengl")]
+mod opengl;
+#[cfg(feature = "terminal")]
+mod terminal;
+
+pub use self::headless::{HeadlessBackend, Image};
+#[cfg(feature = "opengl")]
+pub(crate) use self::opengl::create_grid;
+#[cfg(feature = "opengl")]
+pub use self::opengl::{create_window, get_dpi, get_physical_dimensions, GliumBackend, RogueFont};
+#[cfg(feature = "terminal")]
+pub use self::terminal::{ColourMode, TerminalBackend};
+
+/// A renderer that presents consoles on a screen and delivers the input for that screen
+pub trait Backend {
+    /// Use a font for drawing purposes, backends that do not draw glyphs may ignore it
+    fn add_font(&mut self, name: String, font: Font<'static>, scale: f32) -> Result<()>;
+
+    /// Use a bitmap tileset as a font, the scale multiplies the tile size,
+    /// backends that do not draw glyphs may ignore it
+    fn add_tileset(&mut self, name: String, tileset: Tileset, scale: f32) -> Result<()>;
+
+    /// Grid of the cells on the screen for the font, with the offset and scale of a fixed
+    /// size console
+    fn line_grid(&self, font: &str) -> Result<LineGrid>;
+
+    /// Number of cells in the x and y direction that fit on the screen for the font
+    fn grid_size(&self, font: &str) -> Result<(u32, u32)> {
+        let totals = self.line_grid(font)?.totals();
+        Ok((totals.x, totals.y))
+    }
+
+    /// Present a frame of cells, cells that do not fit on the screen are skipped
+    fn present(&mut self, font: &str, console: &Console) -> Result<()>;
+
+    /// Deliver the input events that happened since the last call
+    fn poll_events(&mut self, callback: &mut dyn FnMut(Event)) -> Result<()>;
+
+    /// Block until there is input and deliver it, backends that can not wait for input
+    /// poll for it every few milliseconds
+    fn wait_events(&mut self, callback: &mut dyn FnMut(Event)) -> Result<()> {
+        loop {
+            let mut received = false;
+            self.poll_events(&mut |event| {
+                received = true;
+                callback(event);
+            })?;
+            if received {
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Keep the number of columns and rows the same when the screen is resized, or fill the
+    /// screen with as many cells as fit with `None`
+    fn set_fixed_size(&mut self, size: Option<FixedSize>);
+
+    /// Rasterize the glyphs for these characters before they are drawn,
+    /// backends without a glyph atlas ignore it
+    fn prewarm(&mut self, _font: &str, _chars: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Draw lines between the cells of the grid, or stop drawing them with `None`,
+    /// backends that can not draw lines ignore it
+    fn set_grid_overlay(&mut self, _overlay: Option<GridOverlay>) {}
+}
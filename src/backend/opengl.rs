@@ -0,0 +1,782 @@
+use crate::atlas::{AtlasConfig, AtlasStats, GlyphAtlas};
+use crate::backend::Backend;
+use crate::buffers::{CellBuffers, CellPrograms};
+use crate::console::{Cell, Console};
+use crate::error::{Result, RoguelibError};
+use crate::input::{Event, Key, Modifiers, MouseButton, MouseTracker};
+use crate::tileset::Tileset;
+use crate::util::{FixedSize, GridOverlay, LineGrid};
+use crate::vertex::{rescale, Vertex};
+use glium::{glutin, Surface};
+use rusttype::{Font, Scale, Vector};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+/// Backend that renders the console with OpenGL into a glutin window
+pub struct GliumBackend {
+    fonts: HashMap<String, RogueFont>,
+    grid_program: glium::Program,
+    background_program: glium::Program,
+    text_program: glium::Program,
+    instanced_background_program: glium::Program,
+    instanced_text_program: glium::Program,
+    grid_overlay: Option<GridOverlay>,
+    fixed_size: Option<FixedSize>,
+    /// Vertices of the last presented console
+    buffers: Option<CellBuffers>,
+    /// Atlas configuration for TrueType fonts that are added
+    atlas_config: AtlasConfig,
+    /// Keys that are down, glutin reports key repeats as presses without a release
+    held_keys: HashSet<Key>,
+    /// Cell under the cursor in the grid of the last presented frame
+    mouse: MouseTracker,
+    pub display: glium::Display,
+    pub event_loop: glutin::EventsLoop,
+}
+
+/// A font to use with associated glium structures
+pub struct RogueFont {
+    kind: FontKind,
+    /// Size of the font before hidpi scaling
+    size: f32,
+    max_font_height: f32,
+    max_font_width: f32,
+}
+
+/// Source of the glyphs of a font
+enum FontKind {
+    /// TrueType font, glyphs are rasterized into the atlas on demand
+    TrueType {
+        font: Font<'static>,
+        atlas: Box<GlyphAtlas>,
+        scale: Scale,
+    },
+    /// Bitmap tileset, the whole sheet is uploaded into the texture at once
+    Tileset {
+        tileset: Tileset,
+        texture: glium::texture::Texture2d,
+    },
+}
+
+impl RogueFont {
+    /// Create the font scaled by the hidpi factor, with an empty glyph atlas
+    fn new(
+        display: &glium::Display,
+        font: Font<'static>,
+        size: f32,
+        atlas_config: AtlasConfig,
+    ) -> Result<RogueFont> {
+        let size = crate::util::check_scale(size)?;
+        let (scale, cell_dimensions) = scaled_cells(display, &font, size)?;
+        let atlas = Box::new(GlyphAtlas::new(display, atlas_config)?);
+
+        Ok(RogueFont {
+            kind: FontKind::TrueType { font, atlas, scale },
+            size,
+            max_font_height: cell_dimensions.y,
+            max_font_width: cell_dimensions.x,
+        })
+    }
+
+    /// Create a font from a tileset, the tiles are scaled by the size and the hidpi factor
+    fn from_tileset(display: &glium::Display, tileset: Tileset, size: f32) -> Result<RogueFont> {
+        let size = crate::util::check_scale(size)?;
+        let cell_dimensions = tile_cells(display, &tileset, size);
+        let (width, height) = tileset.dimensions();
+
+        // Upload the whole sheet
+        let texture = glium::texture::Texture2d::with_format(
+            display,
+            glium::texture::RawImage2d {
+                data: Cow::Borrowed(tileset.coverage()),
+                width,
+                height,
+                format: glium::texture::ClientFormat::U8,
+            },
+            glium::texture::UncompressedFloatFormat::U8,
+            glium::texture::MipmapsOption::NoMipmap,
+        )
+        .map_err(RoguelibError::context)?;
+
+        Ok(RogueFont {
+            size,
+            max_font_height: cell_dimensions.y,
+            max_font_width: cell_dimensions.x,
+            kind: FontKind::Tileset { tileset, texture },
+        })
+    }
+
+    /// Update the font for the current window size and hidpi factor, the glyph atlas keeps
+    /// its size and stats and the tileset keeps its texture. Glyphs of the previous scale are
+    /// evicted once the atlas is full
+    fn rebuild(&mut self, display: &glium::Display) -> Result<()> {
+        let cell_dimensions = match &mut self.kind {
+            FontKind::TrueType { font, scale, .. } => {
+                let (new_scale, cell_dimensions) = scaled_cells(display, font, self.size)?;
+                *scale = new_scale;
+                cell_dimensions
+            }
+            FontKind::Tileset { tileset, .. } => tile_cells(display, tileset, self.size),
+        };
+        self.max_font_height = cell_dimensions.y;
+        self.max_font_width = cell_dimensions.x;
+        Ok(())
+    }
+
+    /// Create the line grid for this font on a screen with the given physical dimensions
+    fn line_grid(&self, width: u32, height: u32, fixed_size: Option<FixedSize>) -> LineGrid {
+        let cell_dimensions = Vector {
+            x: self.max_font_width,
+            y: self.max_font_height,
+        };
+        let grid = match &self.kind {
+            FontKind::TrueType { font, scale, .. } => {
+                crate::util::font_line_grid(font, *scale, &cell_dimensions, width, height)
+            }
+            FontKind::Tileset { .. } => {
+                crate::util::tile_line_grid(&cell_dimensions, width, height)
+            }
+        };
+        match fixed_size {
+            Some(size) => grid.fixed(&size),
+            None => grid,
+        }
+    }
+
+    /// Texture the glyphs are drawn from
+    fn texture(&self) -> &glium::texture::Texture2d {
+        match &self.kind {
+            FontKind::TrueType { atlas, .. } => atlas.texture(),
+            FontKind::Tileset { texture, .. } => texture,
+        }
+    }
+
+    /// Usage of the glyph atlas, tilesets have no atlas
+    pub fn atlas_stats(&self) -> Option<AtlasStats> {
+        match &self.kind {
+            FontKind::TrueType { atlas, .. } => Some(atlas.stats()),
+            FontKind::Tileset { .. } => None,
+        }
+    }
+}
+
+/// Scale of a TrueType font of a size at the hidpi factor, with the dimensions of its cells
+fn scaled_cells(
+    display: &glium::Display,
+    font: &Font<'static>,
+    size: f32,
+) -> Result<(Scale, Vector<f32>)> {
+    let scale = Scale::uniform(size * get_dpi(display) as f32);
+    Ok((scale, crate::util::cell_dimensions(font, scale)?))
+}
+
+/// Dimensions of the cells of a tileset scaled by a size and the hidpi factor
+fn tile_cells(display: &glium::Display, tileset: &Tileset, size: f32) -> Vector<f32> {
+    let scale = size * get_dpi(display) as f32;
+    Vector {
+        x: tileset.tile_width() as f32 * scale,
+        y: tileset.tile_height() as f32 * scale,
+    }
+}
+
+/// Create a window to use with the roguelib library
+pub fn create_window<S: Into<String>>(title: S) -> glutin::WindowBuilder {
+    glutin::WindowBuilder::new()
+        .with_dimensions((1920, 1080).into())
+        .with_title(title)
+}
+
+/// Retrieve physical dimension for the display
+pub fn get_physical_dimensions(display: &glium::Display) -> Result<(u32, u32)> {
+    let dimensions: (u32, u32) = display
+        .gl_window()
+        .window()
+        .get_inner_size()
+        .ok_or_else(|| RoguelibError::context("window no longer exists"))?
+        .to_physical(get_dpi(display))
+        .into();
+    Ok(dimensions)
+}
+
+/// Get dpi factor
+pub fn get_dpi(display: &glium::Display) -> f64 {
+    display.gl_window().window().get_hidpi_factor()
+}
+
+impl GliumBackend {
+    /// Open a window and create the glium structures
+    pub fn new(s: &str) -> Result<GliumBackend> {
+        GliumBackend::with_context(
+            create_window(s),
+            glutin::ContextBuilder::new().with_vsync(true),
+        )
+    }
+
+    /// Open a window with a specific configuration, for example without vsync
+    pub fn with_context(
+        window: glutin::WindowBuilder,
+        context: glutin::ContextBuilder<glutin::NotCurrent>,
+    ) -> Result<GliumBackend> {
+        let event_loop = glutin::EventsLoop::new();
+        let display =
+            glium::Display::new(window, context, &event_loop).map_err(RoguelibError::context)?;
+
+        // Create the shaders for the grid
+        let grid_program = crate::program::create_grid_program(&display)?;
+        // Create the shaders for the cell backgrounds
+        let background_program = crate::program::create_background_program(&display)?;
+        // Create the shaders for the text rendering
+        let text_program = crate::program::create_text_program(&display)?;
+        // Create the shaders that draw every cell as an instance of a single quad
+        let instanced_background_program =
+            crate::program::create_instanced_background_program(&display)?;
+        let instanced_text_program = crate::program::create_instanced_text_program(&display)?;
+
+        Ok(GliumBackend {
+            fonts: HashMap::new(),
+            grid_program,
+            background_program,
+            text_program,
+            instanced_background_program,
+            instanced_text_program,
+            // Only debug builds show the grid unless asked for
+            grid_overlay: if cfg!(debug_assertions) {
+                Some(GridOverlay::default())
+            } else {
+                None
+            },
+            fixed_size: None,
+            buffers: None,
+            atlas_config: AtlasConfig::default(),
+            held_keys: HashSet::new(),
+            mouse: MouseTracker::default(),
+            display,
+            event_loop,
+        })
+    }
+
+    /// Use a different glyph atlas configuration for the TrueType fonts that are added later
+    pub fn with_atlas(mut self, atlas_config: AtlasConfig) -> GliumBackend {
+        self.atlas_config = atlas_config;
+        self
+    }
+
+    /// The font with the given name
+    pub fn font(&self, name: &str) -> Result<&RogueFont> {
+        self.fonts
+            .get(name)
+            .ok_or_else(|| RoguelibError::MissingFont(name.into()))
+    }
+
+    /// Resize the context and rebuild the fonts for the current window size and hidpi factor
+    fn resize(&mut self) -> Result<()> {
+        let dpi = get_dpi(&self.display);
+        if let Some(logical_size) = self.display.gl_window().window().get_inner_size() {
+            self.display
+                .gl_window()
+                .resize(logical_size.to_physical(dpi));
+        }
+
+        // A minimized window has no area to cache glyphs for
+        match get_physical_dimensions(&self.display) {
+            Ok((width, height)) if width > 0 && height > 0 => {}
+            _ => return Ok(()),
+        }
+
+        let display = &self.display;
+        for font in self.fonts.values_mut() {
+            font.rebuild(display)?;
+        }
+        self.buffers = None;
+        Ok(())
+    }
+}
+
+impl Backend for GliumBackend {
+    fn add_font(&mut self, name: String, font: Font<'static>, scale: f32) -> Result<()> {
+        let font = RogueFont::new(&self.display, font, scale, self.atlas_config)?;
+        self.fonts.insert(name, font);
+        // The buffers may point into the texture of a font with the same name
+        self.buffers = None;
+        Ok(())
+    }
+
+    fn add_tileset(&mut self, name: String, tileset: Tileset, scale: f32) -> Result<()> {
+        let font = RogueFont::from_tileset(&self.display, tileset, scale)?;
+        self.fonts.insert(name, font);
+        self.buffers = None;
+        Ok(())
+    }
+
+    fn line_grid(&self, font: &str) -> Result<LineGrid> {
+        let (width, height) = self.display.get_framebuffer_dimensions();
+        Ok(self.font(font)?.line_grid(width, height, self.fixed_size))
+    }
+
+    /// Vertices are kept between frames and only rewritten for cells that changed
+    fn present(&mut self, font_name: &str, console: &Console) -> Result<()> {
+        let font = self
+            .fonts
+            .get_mut(font_name)
+            .ok_or_else(|| RoguelibError::MissingFont(font_name.into()))?;
+
+        let (width, height) = self.display.get_framebuffer_dimensions();
+        let grid = font.line_grid(width, height, self.fixed_size);
+        self.mouse.set_grid(grid);
+
+        // Reuse the buffers of the previous frame when only the cells changed
+        let buffers = match self.buffers.take() {
+            Some(buffers) if buffers.fits(font_name, &grid, console) => buffers,
+            _ => CellBuffers::new(&self.display, font_name, grid, console)?,
+        };
+        let buffers = self.buffers.get_or_insert(buffers);
+
+        match &mut font.kind {
+            FontKind::TrueType {
+                font: true_type,
+                atlas,
+                scale,
+            } => {
+                // Queue every glyph, glyphs that are not queued may be evicted from the atlas
+                for (glyph, _) in crate::util::layout_grid(true_type, *scale, &grid, console) {
+                    atlas.queue(glyph);
+                }
+                // The texture coordinates of unchanged cells are no longer valid
+                if atlas.cache_queued(&self.display)? {
+                    buffers.invalidate();
+                }
+
+                buffers.update(console, |x, y, cell| {
+                    let glyph = crate::util::layout_cell(true_type, *scale, &grid, x, y, cell);
+                    crate::vertex::glyph_rect(&grid, &glyph, atlas)
+                })?;
+            }
+            FontKind::Tileset { tileset, .. } => buffers.update(console, |x, y, cell| {
+                crate::vertex::tile_rect(&grid, x, y, cell, tileset)
+            })?,
+        }
+        buffers.update_grid_lines(&self.display, self.grid_overlay)?;
+        let buffers = &*buffers;
+
+        // Set the text uniforms
+        let text_uniforms = uniform! {
+            tex: font.texture().sampled().magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest)
+        };
+
+        // building the uniforms for the grid program
+        let uniforms_grid = uniform! {
+                matrix: [
+                    [1.0, 0.0, 0.0, 0.0],
+                    [0.0, 1.0, 0.0, 0.0],
+                    [0.0, 0.0, 1.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0f32]
+                ]
+        };
+
+        let mut target = self.display.draw();
+        target.clear_color(0.0, 0.0, 0.0, 1.0);
+
+        let grid_program = &self.grid_program;
+        let background_programs = CellPrograms {
+            quads: &self.background_program,
+            instanced: &self.instanced_background_program,
+        };
+        let text_programs = CellPrograms {
+            quads: &self.text_program,
+            instanced: &self.instanced_text_program,
+        };
+
+        // Draw the cell backgrounds
+        let drawn = buffers
+            .draw_backgrounds(&mut target, background_programs)
+            // Draw the grid lines
+            .and_then(|_| match buffers.grid_lines() {
+                Some((vb_grid, ib_grid)) => target
+                    .draw(
+                        vb_grid,
+                        ib_grid,
+                        grid_program,
+                        &uniforms_grid,
+                        &glium::DrawParameters {
+                            blend: glium::Blend::alpha_blending(),
+                            ..Default::default()
+                        },
+                    )
+                    .map_err(RoguelibError::draw),
+                None => Ok(()),
+            })
+            // Draw the text
+            .and_then(|_| buffers.draw_text(&mut target, text_programs, &text_uniforms));
+
+        // The frame has to be finished, even when drawing failed
+        target.finish().map_err(RoguelibError::draw)?;
+        drawn
+    }
+
+    /// The fonts and context are rebuilt when the window size or hidpi factor changes
+    fn poll_events(&mut self, callback: &mut dyn FnMut(Event)) -> Result<()> {
+        let display = &self.display;
+        let held_keys = &mut self.held_keys;
+        let mouse = &mut self.mouse;
+        let mut resized = false;
+        self.event_loop.poll_events(|event| {
+            if let glutin::Event::WindowEvent { event, .. } = event {
+                resized |= handle_window_event(display, held_keys, mouse, event, callback);
+            }
+        });
+
+        if resized {
+            self.resize()?;
+        }
+        Ok(())
+    }
+
+    /// Sleeps until a window event arrives, then delivers the events that arrived with it
+    fn wait_events(&mut self, callback: &mut dyn FnMut(Event)) -> Result<()> {
+        let display = &self.display;
+        let held_keys = &mut self.held_keys;
+        let mouse = &mut self.mouse;
+        let mut resized = false;
+        self.event_loop.run_forever(|event| match event {
+            glutin::Event::WindowEvent { event, .. } => {
+                resized |= handle_window_event(display, held_keys, mouse, event, callback);
+                glutin::ControlFlow::Break
+            }
+            _ => glutin::ControlFlow::Continue,
+        });
+
+        if resized {
+            self.resize()?;
+        }
+        self.poll_events(callback)
+    }
+
+    fn set_fixed_size(&mut self, size: Option<FixedSize>) {
+        self.fixed_size = size;
+    }
+
+    /// Glyphs are drawn at whole pixels, so the glyphs rasterized for the first cell of the
+    /// grid are used by every cell
+    fn prewarm(&mut self, font_name: &str, chars: &str) -> Result<()> {
+        let font = self
+            .fonts
+            .get_mut(font_name)
+            .ok_or_else(|| RoguelibError::MissingFont(font_name.into()))?;
+        let (width, height) = self.display.get_framebuffer_dimensions();
+        let grid = font.line_grid(width, height, self.fixed_size);
+
+        if let FontKind::TrueType {
+            font: true_type,
+            atlas,
+            scale,
+        } = &mut font.kind
+        {
+            for glyph in chars.chars() {
+                let cell = Cell {
+                    glyph,
+                    ..Cell::default()
+                };
+                atlas.queue(crate::util::layout_cell(
+                    true_type, *scale, &grid, 0, 0, &cell,
+                ));
+            }
+            atlas.cache_queued(&self.display)?;
+            // Only the prewarmed glyphs were queued, so the atlas may have dropped the glyphs
+            // of the last frame to make room even when nothing moved
+            self.buffers = None;
+        }
+        Ok(())
+    }
+
+    fn set_grid_overlay(&mut self, overlay: Option<GridOverlay>) {
+        self.grid_overlay = overlay;
+    }
+}
+
+/// Create a grid, lines for easy display
+pub(crate) fn create_grid(
+    rogue_grid: &LineGrid,
+    overlay: &GridOverlay,
+    display: &glium::Display,
+) -> Result<(glium::VertexBuffer<Vertex>, glium::IndexBuffer<u32>)> {
+    let mut vertices: Vec<Vertex> = Vec::new();
+
+    let screen_width = rogue_grid.screen_dimensions().x;
+    let screen_height = rogue_grid.screen_dimensions().y;
+    let totals = rogue_grid.totals();
+    let color = overlay.colour.into();
+
+    // Lines stay inside of the console, a fixed size console does not cover the whole screen
+    let first = rogue_grid.cell_rect(0, 0).min;
+    let last = rogue_grid.cell_rect(totals.x, totals.y).min;
+    let (left, right) = (
+        rescale(first.x / screen_width),
+        rescale(last.x / screen_width),
+    );
+    let (top, bottom) = (
+        -rescale(first.y / screen_height),
+        -rescale(last.y / screen_height),
+    );
+
+    // Create vertex at ends of the console on the top edge of every Nth row and the bottom edge
+    for y in overlay.lines(totals.y) {
+        let position = -rescale(rogue_grid.cell_rect(0, y).min.y / screen_height);
+        vertices.push(Vertex {
+            position: [left, position],
+            color,
+        });
+        vertices.push(Vertex {
+            position: [right, position],
+            color,
+        });
+    }
+
+    // Create vertex at ends of the console on the left edge of every Nth column and the right edge
+    for x in overlay.lines(totals.x) {
+        let position = rescale(rogue_grid.cell_rect(x, 0).min.x / screen_width);
+        vertices.push(Vertex {
+            position: [position, top],
+            color,
+        });
+        vertices.push(Vertex {
+            position: [position, bottom],
+            color,
+        });
+    }
+
+    let vb = glium::VertexBuffer::new(display, &vertices).map_err(RoguelibError::draw)?;
+    // Every pair of vertices is a line
+    let indices: Vec<u32> = (0..vertices.len() as u32).collect();
+    let ib = glium::IndexBuffer::new(display, glium::index::PrimitiveType::LinesList, &indices)
+        .map_err(RoguelibError::draw)?;
+
+    Ok((vb, ib))
+}
+
+/// Deliver the roguelib events for a glutin window event, returns whether the window was
+/// resized
+fn handle_window_event(
+    display: &glium::Display,
+    held_keys: &mut HashSet<Key>,
+    mouse: &mut MouseTracker,
+    event: glutin::WindowEvent,
+    callback: &mut dyn FnMut(Event),
+) -> bool {
+    use glium::glutin::WindowEvent;
+    let resized = matches!(
+        event,
+        WindowEvent::Resized(_) | WindowEvent::HiDpiFactorChanged(_)
+    );
+    match event {
+        WindowEvent::CursorMoved { .. }
+        | WindowEvent::CursorLeft { .. }
+        | WindowEvent::MouseInput { .. }
+        | WindowEvent::MouseWheel { .. } => translate_mouse_event(display, mouse, event, callback),
+        event => {
+            if let Some(event) = translate_event(display, held_keys, event) {
+                callback(event);
+            }
+        }
+    }
+    resized
+}
+
+/// Translate a glutin window event into a roguelib event, the held keys tell key repeats
+/// apart from presses
+fn translate_event(
+    display: &glium::Display,
+    held_keys: &mut HashSet<Key>,
+    event: glutin::WindowEvent,
+) -> Option<Event> {
+    use glium::glutin::WindowEvent;
+    match event {
+        WindowEvent::CloseRequested => Some(Event::CloseRequested),
+        WindowEvent::Refresh => Some(Event::Refresh),
+        WindowEvent::Resized(logical_size) => {
+            let (width, height) = logical_size.to_physical(get_dpi(display)).into();
+            Some(Event::Resized { width, height })
+        }
+        WindowEvent::HiDpiFactorChanged(_) => get_physical_dimensions(display)
+            .ok()
+            .map(|(width, height)| Event::Resized { width, height }),
+        WindowEvent::KeyboardInput {
+            input:
+                glutin::KeyboardInput {
+                    state,
+                    virtual_keycode: Some(keycode),
+                    modifiers,
+                    ..
+                },
+            ..
+        } => translate_key(keycode).map(|key| {
+            let pressed = state == glutin::ElementState::Pressed;
+            let repeat = if pressed {
+                !held_keys.insert(key)
+            } else {
+                held_keys.remove(&key);
+                false
+            };
+            Event::Key {
+                key,
+                pressed,
+                repeat,
+                modifiers: Modifiers {
+                    shift: modifiers.shift,
+                    ctrl: modifiers.ctrl,
+                    alt: modifiers.alt,
+                    logo: modifiers.logo,
+                },
+            }
+        }),
+        // Control characters are reported as keys
+        WindowEvent::ReceivedCharacter(c) if !c.is_control() => Some(Event::Char(c)),
+        WindowEvent::Focused(focused) => {
+            if !focused {
+                held_keys.clear();
+            }
+            Some(Event::Focused(focused))
+        }
+        _ => None,
+    }
+}
+
+/// Translate a glutin mouse event into roguelib events in cell coordinates, cursor positions
+/// are logical and converted to the physical pixels of the grid
+fn translate_mouse_event(
+    display: &glium::Display,
+    mouse: &mut MouseTracker,
+    event: glutin::WindowEvent,
+    callback: &mut dyn FnMut(Event),
+) {
+    use glium::glutin::WindowEvent;
+    match event {
+        WindowEvent::CursorMoved { position, .. } => {
+            let (x, y): (f64, f64) = position.to_physical(get_dpi(display)).into();
+            mouse.moved(
+                Vector {
+                    x: x as f32,
+                    y: y as f32,
+                },
+                callback,
+            );
+        }
+        WindowEvent::CursorLeft { .. } => mouse.left(callback),
+        WindowEvent::MouseInput { state, button, .. } => callback(Event::MouseButton {
+            button: match button {
+                glutin::MouseButton::Left => MouseButton::Left,
+                glutin::MouseButton::Right => MouseButton::Right,
+                glutin::MouseButton::Middle => MouseButton::Middle,
+                glutin::MouseButton::Other(n) => MouseButton::Other(n),
+            },
+            pressed: state == glutin::ElementState::Pressed,
+            cell: mouse.cell(),
+        }),
+        WindowEvent::MouseWheel { delta, .. } => {
+            let lines = match delta {
+                glutin::MouseScrollDelta::LineDelta(_, lines) => lines,
+                // Touchpads scroll by pixels, a line is the height of a cell
+                glutin::MouseScrollDelta::PixelDelta(position) => {
+                    let pixels = position.to_physical(get_dpi(display)).y as f32;
+                    pixels / mouse.line_height().unwrap_or(1.0)
+                }
+            };
+            callback(Event::MouseWheel {
+                lines,
+                cell: mouse.cell(),
+            });
+        }
+        _ => {}
+    }
+}
+
+/// Translate a glutin key code into a roguelib key
+fn translate_key(keycode: glutin::VirtualKeyCode) -> Option<Key> {
+    use glium::glutin::VirtualKeyCode as V;
+    let key = match keycode {
+        V::A => Key::A,
+        V::B => Key::B,
+        V::C => Key::C,
+        V::D => Key::D,
+        V::E => Key::E,
+        V::F => Key::F,
+        V::G => Key::G,
+        V::H => Key::H,
+        V::I => Key::I,
+        V::J => Key::J,
+        V::K => Key::K,
+        V::L => Key::L,
+        V::M => Key::M,
+        V::N => Key::N,
+        V::O => Key::O,
+        V::P => Key::P,
+        V::Q => Key::Q,
+        V::R => Key::R,
+        V::S => Key::S,
+        V::T => Key::T,
+        V::U => Key::U,
+        V::V => Key::V,
+        V::W => Key::W,
+        V::X => Key::X,
+        V::Y => Key::Y,
+        V::Z => Key::Z,
+        V::Key0 => Key::Key0,
+        V::Key1 => Key::Key1,
+        V::Key2 => Key::Key2,
+        V::Key3 => Key::Key3,
+        V::Key4 => Key::Key4,
+        V::Key5 => Key::Key5,
+        V::Key6 => Key::Key6,
+        V::Key7 => Key::Key7,
+        V::Key8 => Key::Key8,
+        V::Key9 => Key::Key9,
+        V::Numpad0 => Key::Numpad0,
+        V::Numpad1 => Key::Numpad1,
+        V::Numpad2 => Key::Numpad2,
+        V::Numpad3 => Key::Numpad3,
+        V::Numpad4 => Key::Numpad4,
+        V::Numpad5 => Key::Numpad5,
+        V::Numpad6 => Key::Numpad6,
+        V::Numpad7 => Key::Numpad7,
+        V::Numpad8 => Key::Numpad8,
+        V::Numpad9 => Key::Numpad9,
+        V::F1 => Key::F1,
+        V::F2 => Key::F2,
+        V::F3 => Key::F3,
+        V::F4 => Key::F4,
+        V::F5 => Key::F5,
+        V::F6 => Key::F6,
+        V::F7 => Key::F7,
+        V::F8 => Key::F8,
+        V::F9 => Key::F9,
+        V::F10 => Key::F10,
+        V::F11 => Key::F11,
+        V::F12 => Key::F12,
+        V::Left => Key::Left,
+        V::Right => Key::Right,
+        V::Up => Key::Up,
+        V::Down => Key::Down,
+        V::Home => Key::Home,
+        V::End => Key::End,
+        V::PageUp => Key::PageUp,
+        V::PageDown => Key::PageDown,
+        V::Insert => Key::Insert,
+        V::Delete => Key::Delete,
+        V::Escape => Key::Escape,
+        V::Return | V::NumpadEnter => Key::Enter,
+        V::Space => Key::Space,
+        V::Tab => Key::Tab,
+        V::Back => Key::Backspace,
+        V::Comma => Key::Comma,
+        V::Period => Key::Period,
+        V::Slash => Key::Slash,
+        V::Semicolon => Key::Semicolon,
+        V::Minus => Key::Minus,
+        V::Equals => Key::Equals,
+        V::LShift | V::RShift => Key::Shift,
+        V::LControl | V::RControl => Key::Ctrl,
+        V::LAlt | V::RAlt => Key::Alt,
+        V::LWin | V::RWin => Key::Logo,
+        _ => return None,
+    };
+    Some(key)
+}
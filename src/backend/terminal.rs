@@ -0,0 +1,639 @@
+use crate::backend::Backend;
+use crate::colour::Colour;
+use crate::console::{Cell, Console};
+use crate::error::{Result, RoguelibError};
+use crate::input::{Event, Key, Modifiers, MouseButton, MouseTracker};
+use crate::tileset::Tileset;
+use crate::util::{FixedSize, LineGrid};
+use rusttype::{Font, Vector};
+use std::io::{self, Write};
+use termion::event::{Event as TermEvent, Key as TermKey, MouseButton as TermButton, MouseEvent};
+use termion::input::{Events, MouseTerminal, TermRead};
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::{clear, color, cursor, style, AsyncReader};
+
+/// How colours are written to the terminal
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColourMode {
+    /// 24-bit colour escape sequences
+    TrueColour,
+    /// The xterm 256 colour palette
+    Ansi256,
+}
+
+impl ColourMode {
+    /// Detect the colour mode from the `COLORTERM` environment variable
+    pub fn detect() -> ColourMode {
+        match std::env::var("COLORTERM") {
+            Ok(ref value) if value == "truecolor" || value == "24bit" => ColourMode::TrueColour,
+            _ => ColourMode::Ansi256,
+        }
+    }
+}
+
+/// Backend that renders the console to a tty with ANSI escape sequences,
+/// every character of the terminal is a single cell of the grid. The mouse is reported while
+/// it is over the terminal, its movement only while a button is held
+pub struct TerminalBackend {
+    stdout: MouseTerminal<RawTerminal<io::Stdout>>,
+    events: Events<AsyncReader>,
+    size: (u16, u16),
+    fixed_size: Option<FixedSize>,
+    frames: FrameWriter,
+    mouse: MouseTracker,
+    /// Terminals do not report which button was released
+    mouse_button: Option<MouseButton>,
+}
+
+impl TerminalBackend {
+    /// Switch the terminal to raw mode and take over the screen
+    pub fn new() -> Result<TerminalBackend> {
+        let size = termion::terminal_size().map_err(RoguelibError::context)?;
+        let mut stdout = MouseTerminal::from(
+            io::stdout()
+                .into_raw_mode()
+                .map_err(RoguelibError::context)?,
+        );
+        write!(stdout, "{}{}", cursor::Hide, clear::All).map_err(RoguelibError::context)?;
+        stdout.flush().map_err(RoguelibError::context)?;
+
+        Ok(TerminalBackend {
+            stdout,
+            events: termion::async_stdin().events(),
+            size,
+            fixed_size: None,
+            frames: FrameWriter::new(ColourMode::detect()),
+            mouse: MouseTracker::default(),
+            mouse_button: None,
+        })
+    }
+
+    /// Override the detected colour mode
+    pub fn with_colour_mode(mut self, colour_mode: ColourMode) -> TerminalBackend {
+        self.frames = FrameWriter::new(colour_mode);
+        self
+    }
+
+    /// The line grid of the terminal, a cell is a single character
+    /// and a fixed size console is centred without scaling
+    fn grid(&self) -> LineGrid {
+        let grid = LineGrid::new(
+            &Vector {
+                x: f32::from(self.size.0),
+                y: f32::from(self.size.1),
+            },
+            &Vector { x: 1.0, y: 1.0 },
+            &Vector { x: 0.0, y: 0.0 },
+        );
+        match self.fixed_size {
+            Some(size) => grid.letterbox(size.width, size.height, 1.0),
+            None => grid,
+        }
+    }
+
+    /// Translate a termion mouse event into roguelib events, terminals report 1 based
+    /// character positions
+    fn translate_mouse_event(&mut self, event: MouseEvent, callback: &mut dyn FnMut(Event)) {
+        let (x, y) = match event {
+            MouseEvent::Press(_, x, y) | MouseEvent::Release(x, y) | MouseEvent::Hold(x, y) => {
+                (x, y)
+            }
+        };
+        self.mouse.moved(
+            Vector {
+                x: f32::from(x.saturating_sub(1)),
+                y: f32::from(y.saturating_sub(1)),
+            },
+            callback,
+        );
+
+        let cell = self.mouse.cell();
+        match event {
+            MouseEvent::Press(TermButton::WheelUp, ..) => {
+                callback(Event::MouseWheel { lines: 1.0, cell })
+            }
+            MouseEvent::Press(TermButton::WheelDown, ..) => {
+                callback(Event::MouseWheel { lines: -1.0, cell })
+            }
+            MouseEvent::Press(button, ..) => {
+                let button = match button {
+                    TermButton::Right => MouseButton::Right,
+                    TermButton::Middle => MouseButton::Middle,
+                    _ => MouseButton::Left,
+                };
+                self.mouse_button = Some(button);
+                callback(Event::MouseButton {
+                    button,
+                    pressed: true,
+                    cell,
+                });
+            }
+            MouseEvent::Release(..) => {
+                if let Some(button) = self.mouse_button.take() {
+                    callback(Event::MouseButton {
+                        button,
+                        pressed: false,
+                        cell,
+                    });
+                }
+            }
+            MouseEvent::Hold(..) => {}
+        }
+    }
+}
+
+/// Writes frames as escape sequences, only the cells that changed since the previous frame
+/// are written
+struct FrameWriter {
+    colour_mode: ColourMode,
+    /// Dimensions and cells of the last written frame, used for diffing
+    previous: Option<(u32, u32, Vec<Cell>)>,
+}
+
+impl FrameWriter {
+    fn new(colour_mode: ColourMode) -> FrameWriter {
+        FrameWriter {
+            colour_mode,
+            previous: None,
+        }
+    }
+
+    /// Write the whole frame the next time, for example when the terminal was resized
+    fn clear(&mut self) {
+        self.previous = None;
+    }
+
+    /// Write the escape sequence for a foreground or background colour
+    fn write_colour<W: Write>(
+        &self,
+        out: &mut W,
+        colour: Colour,
+        foreground: bool,
+    ) -> io::Result<()> {
+        // The terminal has no transparency, so blend with black
+        let [r, g, b] = [colour.r, colour.g, colour.b]
+            .map(|c| (c * colour.a).clamp(0.0, 1.0))
+            .map(|c| (c * 255.0).round() as u8);
+        match (self.colour_mode, foreground) {
+            (ColourMode::TrueColour, true) => write!(out, "{}", color::Fg(color::Rgb(r, g, b))),
+            (ColourMode::TrueColour, false) => write!(out, "{}", color::Bg(color::Rgb(r, g, b))),
+            (ColourMode::Ansi256, true) => {
+                write!(out, "{}", color::Fg(color::AnsiValue(ansi256(r, g, b))))
+            }
+            (ColourMode::Ansi256, false) => {
+                write!(out, "{}", color::Bg(color::AnsiValue(ansi256(r, g, b))))
+            }
+        }
+    }
+
+    /// Write the cells of a console laid out in the grid of the terminal that changed since
+    /// the previous frame
+    fn write<W: Write>(
+        &mut self,
+        out: &mut W,
+        grid: &LineGrid,
+        console: &Console,
+    ) -> io::Result<()> {
+        let totals = grid.totals();
+        let screen = grid.screen_dimensions();
+        let previous = match self.previous.take() {
+            Some((width, height, cells))
+                if width == console.width() && height == console.height() =>
+            {
+                Some(cells)
+            }
+            _ => {
+                write!(out, "{}{}", style::Reset, clear::All)?;
+                None
+            }
+        };
+
+        // Position and colours of the terminal cursor after the last write
+        let mut position = None;
+        let mut fg = None;
+        let mut bg = None;
+        for (i, (x, y, cell)) in console.cells().enumerate() {
+            let rect = grid.cell_rect(x, y);
+            // Cells of a fixed size console may not fit on a small terminal
+            if x >= totals.x
+                || y >= totals.y
+                || rect.min.x < 0.0
+                || rect.min.y < 0.0
+                || rect.max.x > screen.x
+                || rect.max.y > screen.y
+            {
+                continue;
+            }
+            let (x, y) = (rect.min.x as u32, rect.min.y as u32);
+            if previous.as_ref().is_some_and(|p| p[i] == *cell) {
+                continue;
+            }
+
+            if position != Some((x, y)) {
+                write!(out, "{}", cursor::Goto(x as u16 + 1, y as u16 + 1))?;
+            }
+            if fg != Some(cell.fg) {
+                self.write_colour(out, cell.fg, true)?;
+                fg = Some(cell.fg);
+            }
+            if bg != Some(cell.bg) {
+                self.write_colour(out, cell.bg, false)?;
+                bg = Some(cell.bg);
+            }
+            // Control characters would move the cursor
+            let glyph = if cell.glyph.is_control() {
+                ' '
+            } else {
+                cell.glyph
+            };
+            write!(out, "{}", glyph)?;
+            position = Some((x + 1, y));
+        }
+
+        write!(out, "{}", style::Reset)?;
+        out.flush()?;
+        self.previous = Some((
+            console.width(),
+            console.height(),
+            console.cells().map(|(_, _, c)| *c).collect(),
+        ));
+        Ok(())
+    }
+}
+
+impl Drop for TerminalBackend {
+    fn drop(&mut self) {
+        // Errors are ignored, the terminal may already be gone
+        let _ = write!(
+            self.stdout,
+            "{}{}{}{}",
+            style::Reset,
+            clear::All,
+            cursor::Goto(1, 1),
+            cursor::Show
+        );
+        let _ = self.stdout.flush();
+    }
+}
+
+impl Backend for TerminalBackend {
+    /// Terminals draw with their own font, so the font is ignored
+    fn add_font(&mut self, _name: String, _font: Font<'static>, _scale: f32) -> Result<()> {
+        Ok(())
+    }
+
+    /// Terminals draw with their own font, so the tileset is ignored
+    fn add_tileset(&mut self, _name: String, _tileset: Tileset, _scale: f32) -> Result<()> {
+        Ok(())
+    }
+
+    fn line_grid(&self, _font: &str) -> Result<LineGrid> {
+        Ok(self.grid())
+    }
+
+    fn present(&mut self, _font: &str, console: &Console) -> Result<()> {
+        let grid = self.grid();
+        self.frames
+            .write(&mut self.stdout, &grid, console)
+            .map_err(RoguelibError::draw)
+    }
+
+    fn set_fixed_size(&mut self, size: Option<FixedSize>) {
+        self.fixed_size = size;
+        self.frames.clear();
+    }
+
+    /// Terminals do not report key releases, so every key press is followed by a release.
+    /// Key repeats of the terminal are reported as separate presses
+    fn poll_events(&mut self, callback: &mut dyn FnMut(Event)) -> Result<()> {
+        let size = termion::terminal_size().map_err(RoguelibError::context)?;
+        if size != self.size {
+            self.size = size;
+            self.frames.clear();
+            callback(Event::Resized {
+                width: u32::from(size.0),
+                height: u32::from(size.1),
+            });
+        }
+
+        self.mouse.set_grid(self.grid());
+        // Input that cannot be parsed is skipped
+        while let Some(event) = self.events.next() {
+            let key = match event {
+                Ok(TermEvent::Key(key)) => key,
+                Ok(TermEvent::Mouse(event)) => {
+                    self.translate_mouse_event(event, callback);
+                    continue;
+                }
+                _ => continue,
+            };
+            key_events(key, callback);
+        }
+        Ok(())
+    }
+}
+
+/// Deliver the events of a key typed in the terminal, a press followed by the typed character
+/// and a release. Ctrl-C asks to close, as raw mode no longer interrupts the program
+fn key_events(key: TermKey, callback: &mut dyn FnMut(Event)) {
+    if key == TermKey::Ctrl('c') {
+        callback(Event::CloseRequested);
+        return;
+    }
+    let translated = translate_key(key);
+    if let Some((key, modifiers)) = translated {
+        callback(Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        });
+    }
+    if let TermKey::Char(c) = key {
+        if !c.is_control() {
+            callback(Event::Char(c));
+        }
+    }
+    if let Some((key, modifiers)) = translated {
+        callback(Event::Key {
+            key,
+            pressed: false,
+            repeat: false,
+            modifiers,
+        });
+    }
+}
+
+/// Translate a termion key into a roguelib key with the modifiers the terminal reports,
+/// shift is only known for letters
+fn translate_key(key: TermKey) -> Option<(Key, Modifiers)> {
+    let modifiers = Modifiers::NONE;
+    let key = match key {
+        TermKey::Char('\n') => Key::Enter,
+        TermKey::Char(c) => {
+            let shift = Modifiers {
+                shift: c.is_uppercase(),
+                ..modifiers
+            };
+            return Key::from_char(c).map(|key| (key, shift));
+        }
+        TermKey::Ctrl(c) => {
+            let ctrl = Modifiers {
+                ctrl: true,
+                ..modifiers
+            };
+            return Key::from_char(c).map(|key| (key, ctrl));
+        }
+        TermKey::Alt(c) => {
+            let alt = Modifiers {
+                alt: true,
+                ..modifiers
+            };
+            return Key::from_char(c).map(|key| (key, alt));
+        }
+        TermKey::F(n @ 1..=12) => {
+            const F_KEYS: [Key; 12] = [
+                Key::F1,
+                Key::F2,
+                Key::F3,
+                Key::F4,
+                Key::F5,
+                Key::F6,
+                Key::F7,
+                Key::F8,
+                Key::F9,
+                Key::F10,
+                Key::F11,
+                Key::F12,
+            ];
+            F_KEYS[n as usize - 1]
+        }
+        TermKey::Left => Key::Left,
+        TermKey::Right => Key::Right,
+        TermKey::Up => Key::Up,
+        TermKey::Down => Key::Down,
+        TermKey::Home => Key::Home,
+        TermKey::End => Key::End,
+        TermKey::PageUp => Key::PageUp,
+        TermKey::PageDown => Key::PageDown,
+        TermKey::Insert => Key::Insert,
+        TermKey::Delete => Key::Delete,
+        TermKey::Backspace => Key::Backspace,
+        TermKey::BackTab => {
+            let shift = Modifiers {
+                shift: true,
+                ..modifiers
+            };
+            return Some((Key::Tab, shift));
+        }
+        TermKey::Esc => Key::Escape,
+        _ => return None,
+    };
+    Some((key, modifiers))
+}
+
+/// Closest colour in the xterm 256 colour palette, either from the 6x6x6 colour cube or the
+/// grayscale ramp
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    // Levels used by the colour cube
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let cube_index = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (i32::from(LEVELS[i]) - i32::from(c)).abs())
+            .unwrap()
+    };
+    let distance = |(r1, g1, b1): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        d(r, r1) + d(g, g1) + d(b, b1)
+    };
+
+    let (ri, gi, bi) = (cube_index(r), cube_index(g), cube_index(b));
+    let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+
+    // Grayscale ramp goes from 8 to 238 in steps of 10
+    let average = (u32::from(r) + u32::from(g) + u32::from(b)) / 3;
+    let gray_index = ((average.saturating_sub(3)) / 10).min(23) as u8;
+    let gray_level = 8 + gray_index * 10;
+
+    if distance((gray_level, gray_level, gray_level)) < distance(cube) {
+        232 + gray_index
+    } else {
+        16 + (36 * ri + 6 * gi + bi) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colours_map_to_the_closest_palette_entry() {
+        assert_eq!(ansi256(0, 0, 0), 16);
+        assert_eq!(ansi256(255, 255, 255), 231);
+        assert_eq!(ansi256(255, 0, 0), 196);
+        assert_eq!(ansi256(95, 135, 175), 67);
+        // Grays between the levels of the cube use the grayscale ramp
+        assert_eq!(ansi256(128, 128, 128), 244);
+        assert_eq!(ansi256(8, 8, 8), 232);
+    }
+
+    #[test]
+    fn keys_are_translated_with_modifiers() {
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::NONE
+        };
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::NONE
+        };
+        let alt = Modifiers {
+            alt: true,
+            ..Modifiers::NONE
+        };
+        assert_eq!(
+            translate_key(TermKey::Char('a')),
+            Some((Key::A, Modifiers::NONE))
+        );
+        assert_eq!(translate_key(TermKey::Char('A')), Some((Key::A, shift)));
+        assert_eq!(
+            translate_key(TermKey::Char('\n')),
+            Some((Key::Enter, Modifiers::NONE))
+        );
+        assert_eq!(translate_key(TermKey::Ctrl('x')), Some((Key::X, ctrl)));
+        assert_eq!(translate_key(TermKey::Alt('1')), Some((Key::Key1, alt)));
+        assert_eq!(
+            translate_key(TermKey::F(12)),
+            Some((Key::F12, Modifiers::NONE))
+        );
+        assert_eq!(translate_key(TermKey::F(13)), None);
+        assert_eq!(translate_key(TermKey::BackTab), Some((Key::Tab, shift)));
+        assert_eq!(
+            translate_key(TermKey::Esc),
+            Some((Key::Escape, Modifiers::NONE))
+        );
+        assert_eq!(translate_key(TermKey::Null), None);
+    }
+
+    /// Events delivered for a key typed in the terminal
+    fn events_for(key: TermKey) -> Vec<Event> {
+        let mut events = Vec::new();
+        key_events(key, &mut |event| events.push(event));
+        events
+    }
+
+    #[test]
+    fn typed_keys_are_pressed_and_released() {
+        let key = |pressed| Event::Key {
+            key: Key::Q,
+            pressed,
+            repeat: false,
+            modifiers: Modifiers::NONE,
+        };
+        assert_eq!(
+            events_for(TermKey::Char('q')),
+            vec![key(true), Event::Char('q'), key(false)]
+        );
+        assert_eq!(events_for(TermKey::Ctrl('c')), vec![Event::CloseRequested]);
+        assert_eq!(events_for(TermKey::Null), vec![]);
+    }
+
+    /// Grid of a terminal of 4 by 2 characters
+    fn grid() -> LineGrid {
+        LineGrid::new(
+            &Vector { x: 4.0, y: 2.0 },
+            &Vector { x: 1.0, y: 1.0 },
+            &Vector { x: 0.0, y: 0.0 },
+        )
+    }
+
+    /// Output of writing a console
+    fn write(frames: &mut FrameWriter, grid: &LineGrid, console: &Console) -> String {
+        let mut out = Vec::new();
+        frames.write(&mut out, grid, console).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn only_changed_cells_are_written() {
+        let mut frames = FrameWriter::new(ColourMode::TrueColour);
+        let grid = grid();
+        let mut console = Console::new(2, 1);
+        console.set(0, 0, 'a', Colour::RED, Colour::BLACK);
+        console.set(1, 0, 'b', Colour::RED, Colour::BLACK);
+
+        // The first frame clears the screen and writes every cell, colours are written once
+        assert_eq!(
+            write(&mut frames, &grid, &console),
+            format!(
+                "{}{}{}{}{}ab{}",
+                style::Reset,
+                clear::All,
+                cursor::Goto(1, 1),
+                color::Fg(color::Rgb(255, 0, 0)),
+                color::Bg(color::Rgb(0, 0, 0)),
+                style::Reset
+            )
+        );
+        assert_eq!(
+            write(&mut frames, &grid, &console),
+            style::Reset.to_string()
+        );
+
+        // Control characters are written as spaces
+        console.set(1, 0, '\t', Colour::WHITE, Colour::BLACK);
+        assert_eq!(
+            write(&mut frames, &grid, &console),
+            format!(
+                "{}{}{} {}",
+                cursor::Goto(2, 1),
+                color::Fg(color::Rgb(255, 255, 255)),
+                color::Bg(color::Rgb(0, 0, 0)),
+                style::Reset
+            )
+        );
+
+        // A console of a different size is written as a whole
+        let console = Console::new(1, 1);
+        let cleared = format!("{}{}", style::Reset, clear::All);
+        assert!(write(&mut frames, &grid, &console).starts_with(&cleared));
+    }
+
+    #[test]
+    fn colours_are_written_in_the_colour_mode() {
+        let grid = grid();
+        let mut console = Console::new(1, 1);
+        // Transparent colours are blended with black
+        console.set(0, 0, 'x', Colour::RED, Colour::rgba(1.0, 1.0, 1.0, 0.0));
+
+        let mut frames = FrameWriter::new(ColourMode::Ansi256);
+        assert_eq!(
+            write(&mut frames, &grid, &console),
+            format!(
+                "{}{}{}{}{}x{}",
+                style::Reset,
+                clear::All,
+                cursor::Goto(1, 1),
+                color::Fg(color::AnsiValue(196)),
+                color::Bg(color::AnsiValue(16)),
+                style::Reset
+            )
+        );
+    }
+
+    #[test]
+    fn cells_outside_of_the_terminal_are_skipped() {
+        let grid = grid().letterbox(6, 1, 1.0);
+        let mut console = Console::new(6, 1);
+        for x in 0..6 {
+            console.set(x, 0, 'x', Colour::WHITE, Colour::BLACK);
+        }
+        let output = write(
+            &mut FrameWriter::new(ColourMode::TrueColour),
+            &grid,
+            &console,
+        );
+        // The console is centred, so only the middle four cells fit
+        assert_eq!(output.matches('x').count(), 4);
+    }
+}
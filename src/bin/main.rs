@@ -1,57 +1,73 @@
-use glium::glutin;
 use std::error::Error;
 
-use roguelib::Roguelib;
+use roguelib::{
+    Action, Bindings, Colour, Console, Context, GameState, InputState, Key, KeyBinding, Roguelib,
+    RunMode, Transition,
+};
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut roguelib = Roguelib::new("roguelike");
-
-    let font_data = include_bytes!("../../fonts/square.ttf");
-
-    roguelib.add_font("default", font_data, 24.0);
-
-    // Receive the inputs
-    let mut finished = false;
-    loop {
-        roguelib.event_loop.poll_events(|event| {
-            use self::glutin::*;
-
-            if let Event::WindowEvent { event, .. } = event {
-                match event {
-                    WindowEvent::CloseRequested => finished = true,
-                    WindowEvent::Resized(_logical_size) => {
-                        //                        let _dpi_factor = display.gl_window().get_hidpi_factor();
-                        //
-                        //                        // Reset the vertex buffers
-                        //                        text_vertex_buffer = crate::util::create_text_vb(display, &glyphs, &cache);
-                        //                        let grid =
-                        //                            crate::util::create_grid(max_font_width, max_font_height, display);
-                        //
-                        //                        vb_grid = grid.0;
-                        //                        ib_grid = grid.1;
-                    }
-                    WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(keypress),
-                                ..
-                            },
-                        ..
-                    } => match keypress {
-                        VirtualKeyCode::Escape => finished = true,
-                        _ => (),
-                    },
-                    _ => {}
-                }
-            }
-        });
-        if finished {
-            break;
+/// The actions of the example
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Command {
+    Quit,
+}
+
+impl Action for Command {
+    fn name(&self) -> &str {
+        match self {
+            Command::Quit => "quit",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "quit" => Some(Command::Quit),
+            _ => None,
         }
+    }
+}
+
+/// The example shows the same content until quit is pressed
+struct Example {
+    bindings: Bindings<Command>,
+}
+
+impl GameState for Example {
+    fn update(&mut self, _ctx: &mut Context, input: &InputState) -> Transition {
+        if self.bindings.is_pressed(input, Command::Quit) {
+            Transition::Quit
+        } else {
+            Transition::Continue
+        }
+    }
+
+    /// Put some example content in the console
+    fn render(&mut self, console: &mut Console) {
+        for (i, c) in "abcdefg".chars().enumerate() {
+            console.set(i as u32 + 1, 1, c, Colour::WHITE, Colour::BLACK);
+        }
+        console.set(9, 1, '@', Colour::YELLOW, Colour::DARK_GREY);
+        console.set(
+            10,
+            1,
+            '■',
+            Colour::from_hex("#8b4513").unwrap(),
+            Colour::BLACK,
+        );
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut roguelib = Roguelib::new("roguelike")?;
 
-        roguelib.draw("default", "abcdefg@■");
+    // Use the font given on the command line, or the bundled one
+    match std::env::args().nth(1) {
+        Some(path) => roguelib.add_font_from_path("default", path, 24.0)?,
+        None => roguelib.add_font("default", include_bytes!("../../fonts/consola.ttf"), 24.0)?,
     }
 
+    // The content only changes with input, so there is no need to draw every frame
+    roguelib.set_run_mode(RunMode::TurnBased);
+    let bindings = Bindings::new().with(KeyBinding::new(Key::Escape), Command::Quit);
+    roguelib.run("default", Example { bindings })?;
     Ok(())
 }
@@ -0,0 +1,513 @@
+use crate::error::{Result, RoguelibError};
+use crate::input::{Event, InputState, Key, Modifiers};
+use std::fmt;
+use std::path::Path;
+
+/// An action of the game that keys are bound to, usually an enum defined by the game
+pub trait Action: Copy + PartialEq {
+    /// Name of the action in binding files, like `move_north`
+    fn name(&self) -> &str;
+
+    /// Action with a name in binding files, None for names that are not actions
+    fn from_name(name: &str) -> Option<Self>;
+}
+
+/// One of the eight directions of movement on the grid
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// Every direction, clockwise from north
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// Change in x and y of a step in the direction, y grows downwards
+    pub fn delta(self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::NorthEast => (1, -1),
+            Direction::East => (1, 0),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (0, 1),
+            Direction::SouthWest => (-1, 1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+}
+
+/// Common layouts of keys for moving in eight directions
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MovementKeys {
+    /// `hjkl` with `yubn` for the diagonals
+    Vi,
+    /// The digits of the numpad around 5
+    Numpad,
+    /// Arrow keys with home, page up, end and page down for the diagonals
+    Arrows,
+}
+
+impl MovementKeys {
+    /// Key for a direction, in the order of `Direction::ALL`
+    fn keys(self) -> [Key; 8] {
+        match self {
+            MovementKeys::Vi => [
+                Key::K,
+                Key::U,
+                Key::L,
+                Key::N,
+                Key::J,
+                Key::B,
+                Key::H,
+                Key::Y,
+            ],
+            MovementKeys::Numpad => [
+                Key::Numpad8,
+                Key::Numpad9,
+                Key::Numpad6,
+                Key::Numpad3,
+                Key::Numpad2,
+                Key::Numpad1,
+                Key::Numpad4,
+                Key::Numpad7,
+            ],
+            MovementKeys::Arrows => [
+                Key::Up,
+                Key::PageUp,
+                Key::Right,
+                Key::PageDown,
+                Key::Down,
+                Key::End,
+                Key::Left,
+                Key::Home,
+            ],
+        }
+    }
+}
+
+/// A key together with the modifiers that have to be held down with it, like `ctrl+s`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl KeyBinding {
+    /// A key without modifiers
+    pub fn new(key: Key) -> KeyBinding {
+        KeyBinding {
+            key,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    /// The same key with modifiers that have to be held down
+    pub fn with_modifiers(self, modifiers: Modifiers) -> KeyBinding {
+        KeyBinding { modifiers, ..self }
+    }
+
+    /// Parse a binding like `ctrl+shift+s`, names are case insensitive
+    pub fn parse(text: &str) -> Result<KeyBinding> {
+        parse_binding(text).map_err(|e| RoguelibError::Bindings(vec![e]))
+    }
+}
+
+/// Parse a binding, the error describes the part that is not a key or modifier
+fn parse_binding(text: &str) -> std::result::Result<KeyBinding, String> {
+    let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    let key = parts.pop().unwrap_or_default();
+    let key = Key::from_name(key).ok_or_else(|| format!("unknown key '{}'", key))?;
+
+    let mut modifiers = Modifiers::NONE;
+    for modifier in parts {
+        let held = match modifier.to_ascii_lowercase().as_str() {
+            "shift" => &mut modifiers.shift,
+            "ctrl" => &mut modifiers.ctrl,
+            "alt" => &mut modifiers.alt,
+            "logo" => &mut modifiers.logo,
+            _ => return Err(format!("unknown modifier '{}'", modifier)),
+        };
+        *held = true;
+    }
+    Ok(KeyBinding { key, modifiers })
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let modifiers = [
+            (self.modifiers.ctrl, "ctrl"),
+            (self.modifiers.alt, "alt"),
+            (self.modifiers.shift, "shift"),
+            (self.modifiers.logo, "logo"),
+        ];
+        for (_, name) in modifiers.iter().filter(|(held, _)| *held) {
+            write!(f, "{}+", name)?;
+        }
+        write!(f, "{}", self.key.name())
+    }
+}
+
+/// Keys bound to the actions of a game, an action can have many bindings but a binding
+/// triggers a single action
+///
+/// Bindings files have a line per action with its bindings separated by commas, like
+/// `move_north = k, up, numpad8`. Empty lines and lines starting with `#` are skipped
+#[derive(Debug, Clone)]
+pub struct Bindings<A: Action> {
+    bindings: Vec<(KeyBinding, A)>,
+}
+
+/// Bindings are equal when they bind the same keys to the same actions, in any order
+impl<A: Action> PartialEq for Bindings<A> {
+    fn eq(&self, other: &Bindings<A>) -> bool {
+        self.bindings.len() == other.bindings.len()
+            && self.bindings.iter().all(|b| other.bindings.contains(b))
+    }
+}
+
+impl<A: Action> Default for Bindings<A> {
+    fn default() -> Bindings<A> {
+        Bindings::new()
+    }
+}
+
+impl<A: Action> Bindings<A> {
+    /// Create a map without bindings
+    pub fn new() -> Bindings<A> {
+        Bindings {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Bind a key to an action, replacing the action the key was bound to
+    pub fn bind(&mut self, binding: KeyBinding, action: A) {
+        self.unbind(binding);
+        self.bindings.push((binding, action));
+    }
+
+    /// Bind a key to an action like `bind`, returning the map
+    pub fn with(mut self, binding: KeyBinding, action: A) -> Bindings<A> {
+        self.bind(binding, action);
+        self
+    }
+
+    /// Bind the keys of a layout for moving in eight directions to the movement actions
+    pub fn bind_movement<F: Fn(Direction) -> A>(&mut self, keys: MovementKeys, action: F) {
+        for (key, direction) in keys.keys().iter().zip(Direction::ALL.iter()) {
+            self.bind(KeyBinding::new(*key), action(*direction));
+        }
+    }
+
+    /// Remove the binding of a key
+    pub fn unbind(&mut self, binding: KeyBinding) {
+        self.bindings.retain(|(b, _)| *b != binding);
+    }
+
+    /// Remove every binding of an action
+    pub fn clear(&mut self, action: A) {
+        self.bindings.retain(|(_, a)| *a != action);
+    }
+
+    /// The keys bound to an action
+    pub fn bindings_for(&self, action: A) -> impl Iterator<Item = KeyBinding> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, a)| *a == action)
+            .map(|(binding, _)| *binding)
+    }
+
+    /// The action bound to a key with exactly these modifiers
+    pub fn action_for(&self, key: Key, modifiers: Modifiers) -> Option<A> {
+        let binding = KeyBinding { key, modifiers };
+        self.bindings
+            .iter()
+            .find(|(b, _)| *b == binding)
+            .map(|(_, action)| *action)
+    }
+
+    /// The action of a key press event, also for key repeats
+    pub fn action(&self, event: &Event) -> Option<A> {
+        match *event {
+            Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+                ..
+            } => self.action_for(key, modifiers),
+            _ => None,
+        }
+    }
+
+    /// Whether a key of the action went down during the frame
+    pub fn is_pressed(&self, input: &InputState, action: A) -> bool {
+        self.matches(input, action, |key| input.is_pressed(key))
+    }
+
+    /// Whether a key of the action went down or was repeated during the frame
+    pub fn is_repeated(&self, input: &InputState, action: A) -> bool {
+        self.matches(input, action, |key| input.is_repeated(key))
+    }
+
+    /// Whether a key of the action is down at the end of the frame
+    pub fn is_held(&self, input: &InputState, action: A) -> bool {
+        self.matches(input, action, |key| input.is_held(key))
+    }
+
+    /// The actions with a key that went down during the frame
+    pub fn pressed<'a>(&'a self, input: &'a InputState) -> impl Iterator<Item = A> + 'a {
+        self.bindings
+            .iter()
+            .filter(move |(b, _)| {
+                input.is_pressed(b.key) && input.key_modifiers(b.key) == Some(b.modifiers)
+            })
+            .map(|(_, action)| *action)
+    }
+
+    /// Whether a key of the action is in the state, with the modifiers it was pressed with
+    fn matches<F: Fn(Key) -> bool>(&self, input: &InputState, action: A, state: F) -> bool {
+        self.bindings_for(action)
+            .any(|b| state(b.key) && input.key_modifiers(b.key) == Some(b.modifiers))
+    }
+
+    /// Parse bindings in the format of bindings files, every malformed line and every key
+    /// that is bound to more than one action is reported with its line number
+    pub fn parse(text: &str) -> Result<Bindings<A>> {
+        let mut bindings: Bindings<A> = Bindings::new();
+        let mut errors = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let number = number + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, keys) = match line.find('=') {
+                Some(i) => (line[..i].trim(), &line[i + 1..]),
+                None => {
+                    errors.push(format!("line {}: expected 'action = keys'", number));
+                    continue;
+                }
+            };
+            let action = match A::from_name(name) {
+                Some(action) => action,
+                None => {
+                    errors.push(format!("line {}: unknown action '{}'", number, name));
+                    continue;
+                }
+            };
+
+            for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+                let binding = match parse_binding(key) {
+                    Ok(binding) => binding,
+                    Err(e) => {
+                        errors.push(format!("line {}: {}", number, e));
+                        continue;
+                    }
+                };
+                match bindings.action_for(binding.key, binding.modifiers) {
+                    Some(other) if other != action => errors.push(format!(
+                        "line {}: '{}' is already bound to '{}'",
+                        number,
+                        binding,
+                        other.name()
+                    )),
+                    _ => bindings.bind(binding, action),
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(bindings)
+        } else {
+            Err(RoguelibError::Bindings(errors))
+        }
+    }
+
+    /// Read the bindings from a bindings file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Bindings<A>> {
+        let path = path.as_ref();
+        let text =
+            std::fs::read_to_string(path).map_err(|e| RoguelibError::Io(path.to_path_buf(), e))?;
+        Bindings::parse(&text)
+    }
+
+    /// Write the bindings to a bindings file, with a line per action
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_string()).map_err(|e| RoguelibError::Io(path.to_path_buf(), e))
+    }
+}
+
+/// The bindings in the format of bindings files, actions are written in the order they were
+/// first bound
+impl<A: Action> fmt::Display for Bindings<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut actions: Vec<A> = Vec::new();
+        for (_, action) in &self.bindings {
+            if !actions.contains(action) {
+                actions.push(*action);
+            }
+        }
+
+        for action in actions {
+            let keys: Vec<String> = self.bindings_for(action).map(|b| b.to_string()).collect();
+            writeln!(f, "{} = {}", action.name(), keys.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    enum Command {
+        Move(Direction),
+        Save,
+        Quit,
+    }
+
+    impl Action for Command {
+        fn name(&self) -> &str {
+            match self {
+                Command::Move(Direction::North) => "move_north",
+                Command::Move(Direction::NorthEast) => "move_north_east",
+                Command::Move(Direction::East) => "move_east",
+                Command::Move(Direction::SouthEast) => "move_south_east",
+                Command::Move(Direction::South) => "move_south",
+                Command::Move(Direction::SouthWest) => "move_south_west",
+                Command::Move(Direction::West) => "move_west",
+                Command::Move(Direction::NorthWest) => "move_north_west",
+                Command::Save => "save",
+                Command::Quit => "quit",
+            }
+        }
+
+        fn from_name(name: &str) -> Option<Command> {
+            Direction::ALL
+                .iter()
+                .map(|d| Command::Move(*d))
+                .chain(vec![Command::Save, Command::Quit])
+                .find(|action| action.name() == name)
+        }
+    }
+
+    /// Messages of the errors of a bindings file
+    fn errors(text: &str) -> Vec<String> {
+        match Bindings::<Command>::parse(text) {
+            Err(RoguelibError::Bindings(errors)) => errors,
+            result => panic!("'{}' gave {:?}", text, result),
+        }
+    }
+
+    #[test]
+    fn save_and_parse() {
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::NONE
+        };
+        let mut bindings = Bindings::new()
+            .with(KeyBinding::new(Key::S).with_modifiers(ctrl), Command::Save)
+            .with(KeyBinding::new(Key::Escape), Command::Quit);
+        bindings.bind_movement(MovementKeys::Vi, Command::Move);
+        bindings.bind_movement(MovementKeys::Arrows, Command::Move);
+
+        let text = bindings.to_string();
+        assert!(text.starts_with("save = ctrl+s\n"));
+        assert!(text.contains("move_north = k, up\n"));
+        assert_eq!(Bindings::parse(&text).unwrap(), bindings);
+    }
+
+    #[test]
+    fn chords_match_the_modifiers_of_the_press() {
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::NONE
+        };
+        let bindings = Bindings::new()
+            .with(KeyBinding::new(Key::S).with_modifiers(ctrl), Command::Save)
+            .with(KeyBinding::new(Key::Escape), Command::Quit);
+        let key = |key, pressed, modifiers| Event::Key {
+            key,
+            pressed,
+            repeat: false,
+            modifiers,
+        };
+
+        // Ctrl is let go before the frame ends, and is held while escape is pressed
+        let mut input = InputState::new();
+        input.handle(&key(Key::Ctrl, true, ctrl));
+        input.handle(&key(Key::S, true, ctrl));
+        input.handle(&key(Key::Ctrl, false, Modifiers::NONE));
+        input.handle(&key(Key::Escape, true, Modifiers::NONE));
+        assert!(bindings.is_pressed(&input, Command::Save));
+        assert!(bindings.is_pressed(&input, Command::Quit));
+        assert_eq!(bindings.pressed(&input).count(), 2);
+
+        input.begin_frame();
+        input.handle(&key(Key::Ctrl, true, ctrl));
+        input.handle(&key(Key::Escape, true, ctrl));
+        assert!(bindings.is_held(&input, Command::Save));
+        assert!(!bindings.is_pressed(&input, Command::Quit));
+        assert!(!bindings.is_held(&input, Command::Quit));
+    }
+
+    #[test]
+    fn parse_skips_comments() {
+        let bindings: Bindings<Command> =
+            Bindings::parse("# movement\n\n  move_west = h , LEFT\n").unwrap();
+        let keys: Vec<KeyBinding> = bindings
+            .bindings_for(Command::Move(Direction::West))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![KeyBinding::new(Key::H), KeyBinding::new(Key::Left)]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            errors("save ctrl+s"),
+            vec!["line 1: expected 'action = keys'"]
+        );
+        assert_eq!(
+            errors("jump = space"),
+            vec!["line 1: unknown action 'jump'"]
+        );
+        assert_eq!(errors("save = ctrl+sss"), vec!["line 1: unknown key 'sss'"]);
+        assert_eq!(
+            errors("save = hyper+s"),
+            vec!["line 1: unknown modifier 'hyper'"]
+        );
+        assert_eq!(
+            errors("save = q\nquit = escape, q"),
+            vec!["line 2: 'q' is already bound to 'save'"]
+        );
+        assert_eq!(
+            errors("# bindings\nsave\nquit = escape\nsave = s, x+y"),
+            vec![
+                "line 2: expected 'action = keys'",
+                "line 4: unknown modifier 'x'"
+            ]
+        );
+    }
+}
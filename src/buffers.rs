@@ -0,0 +1,311 @@
+use crate::console::{Cell, Console};
+use crate::error::{Result, RoguelibError};
+use crate::util::{GridOverlay, LineGrid};
+use crate::vertex::{
+    BackgroundVertex, CellInstance, QuadVertex, TextVertex, TexturedRect, Vertex,
+    EMPTY_BACKGROUND_QUAD,
+};
+use glium::uniforms::Uniforms;
+use glium::Surface;
+
+/// Vertices of a console that stay on the GPU between frames, the vertices of a cell are only
+/// rewritten when the cell changes
+pub(crate) struct CellBuffers {
+    font: String,
+    grid: LineGrid,
+    width: u32,
+    height: u32,
+    /// Cells as they have been written into the buffers, empty when everything must be written
+    cells: Vec<Cell>,
+    storage: Storage,
+    grid_lines: Option<GridLines>,
+}
+
+/// How the cells are stored on the GPU
+enum Storage {
+    /// A single quad drawn for every cell, with one instance of cell data per cell
+    Instanced {
+        quad: glium::VertexBuffer<QuadVertex>,
+        instances: glium::VertexBuffer<CellInstance>,
+    },
+    /// Six background and six text vertices per cell, for contexts without instancing
+    Quads {
+        backgrounds: glium::VertexBuffer<BackgroundVertex>,
+        text: glium::VertexBuffer<TextVertex>,
+    },
+}
+
+/// Lines of the grid overlay, only rebuilt when the overlay changes
+struct GridLines {
+    overlay: GridOverlay,
+    vertices: glium::VertexBuffer<Vertex>,
+    indices: glium::IndexBuffer<u32>,
+}
+
+/// A cell that is written into the buffers, with the rectangle of its glyph
+struct ChangedCell {
+    x: u32,
+    y: u32,
+    cell: Cell,
+    glyph: Option<TexturedRect>,
+}
+
+/// The programs that draw the cells, one for each kind of storage
+#[derive(Copy, Clone)]
+pub(crate) struct CellPrograms<'a> {
+    pub quads: &'a glium::Program,
+    pub instanced: &'a glium::Program,
+}
+
+impl CellBuffers {
+    /// Allocate the buffers for a console drawn with a font in a grid,
+    /// instancing is used when the context supports it
+    pub(crate) fn new(
+        display: &glium::Display,
+        font: &str,
+        grid: LineGrid,
+        console: &Console,
+    ) -> Result<CellBuffers> {
+        let cell_count = console.width() as usize * console.height() as usize;
+        let instances =
+            glium::VertexBuffer::empty_dynamic(display, cell_count).map_err(RoguelibError::draw)?;
+        let storage = if instances.per_instance().is_ok() {
+            let corners = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]];
+            let quad: Vec<QuadVertex> = corners
+                .iter()
+                .map(|&corner| QuadVertex { corner })
+                .collect();
+            Storage::Instanced {
+                quad: glium::VertexBuffer::new(display, &quad).map_err(RoguelibError::draw)?,
+                instances,
+            }
+        } else {
+            Storage::Quads {
+                backgrounds: glium::VertexBuffer::empty_dynamic(display, cell_count * 6)
+                    .map_err(RoguelibError::draw)?,
+                text: glium::VertexBuffer::empty_dynamic(display, cell_count * 6)
+                    .map_err(RoguelibError::draw)?,
+            }
+        };
+
+        Ok(CellBuffers {
+            font: font.into(),
+            grid,
+            width: console.width(),
+            height: console.height(),
+            cells: Vec::new(),
+            storage,
+            grid_lines: None,
+        })
+    }
+
+    /// Whether the buffers can be reused for the console, the font and the grid
+    pub(crate) fn fits(&self, font: &str, grid: &LineGrid, console: &Console) -> bool {
+        self.font == font
+            && self.grid == *grid
+            && self.width == console.width()
+            && self.height == console.height()
+    }
+
+    /// Rewrite every cell on the next update, needed when the glyph cache moved its glyphs
+    pub(crate) fn invalidate(&mut self) {
+        self.cells.clear();
+    }
+
+    /// Rewrite the cells that changed since the last update, runs of changed cells are written
+    /// at once, the glyph rectangle of a cell is only requested when the cell changed
+    pub(crate) fn update<F>(&mut self, console: &Console, mut glyph_rect: F) -> Result<()>
+    where
+        F: FnMut(u32, u32, &Cell) -> Option<TexturedRect>,
+    {
+        let mut run = None;
+        let mut changed = Vec::new();
+        for (i, (x, y, cell)) in console.cells().enumerate() {
+            if self.cells.get(i) == Some(cell) {
+                if let Some(start) = run.take() {
+                    self.write(start, &changed)?;
+                    changed.clear();
+                }
+                continue;
+            }
+
+            run.get_or_insert(i);
+            // Cells that do not fit on the screen are not drawn
+            let glyph = if self.grid.contains(x, y) {
+                glyph_rect(x, y, cell)
+            } else {
+                None
+            };
+            changed.push(ChangedCell {
+                x,
+                y,
+                cell: *cell,
+                glyph,
+            });
+        }
+        if let Some(start) = run {
+            self.write(start, &changed)?;
+        }
+
+        self.cells.clear();
+        self.cells.extend(console.cells().map(|(_, _, cell)| *cell));
+        Ok(())
+    }
+
+    /// Write a run of cells starting at a cell index
+    fn write(&mut self, start: usize, changed: &[ChangedCell]) -> Result<()> {
+        let grid = &self.grid;
+        let out_of_range = || RoguelibError::draw("cell is outside of the cell buffers");
+        match &mut self.storage {
+            Storage::Instanced { instances, .. } => {
+                let data: Vec<CellInstance> =
+                    changed.iter().map(|c| cell_instance(grid, c)).collect();
+                instances
+                    .slice_mut(start..start + changed.len())
+                    .ok_or_else(out_of_range)?
+                    .write(&data);
+            }
+            Storage::Quads { backgrounds, text } => {
+                let mut background_data = Vec::with_capacity(changed.len() * 6);
+                let mut text_data = Vec::with_capacity(changed.len() * 6);
+                for c in changed {
+                    if grid.contains(c.x, c.y) {
+                        background_data.extend_from_slice(&crate::vertex::background_quad(
+                            grid, c.x, c.y, &c.cell,
+                        ));
+                    } else {
+                        background_data.extend_from_slice(&EMPTY_BACKGROUND_QUAD);
+                    }
+                    text_data.extend_from_slice(&crate::vertex::text_quad(c.glyph, c.cell.fg));
+                }
+                let range = start * 6..(start + changed.len()) * 6;
+                backgrounds
+                    .slice_mut(range.clone())
+                    .ok_or_else(out_of_range)?
+                    .write(&background_data);
+                text.slice_mut(range)
+                    .ok_or_else(out_of_range)?
+                    .write(&text_data);
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw the background of every cell
+    pub(crate) fn draw_backgrounds<S: Surface>(
+        &self,
+        target: &mut S,
+        programs: CellPrograms,
+    ) -> Result<()> {
+        self.draw(target, programs, &glium::uniforms::EmptyUniforms, false)
+    }
+
+    /// Draw the glyph of every cell, the uniforms contain the font texture
+    pub(crate) fn draw_text<S: Surface, U: Uniforms>(
+        &self,
+        target: &mut S,
+        programs: CellPrograms,
+        uniforms: &U,
+    ) -> Result<()> {
+        self.draw(target, programs, uniforms, true)
+    }
+
+    fn draw<S: Surface, U: Uniforms>(
+        &self,
+        target: &mut S,
+        programs: CellPrograms,
+        uniforms: &U,
+        text: bool,
+    ) -> Result<()> {
+        let parameters = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            ..Default::default()
+        };
+        let drawn = match &self.storage {
+            Storage::Instanced { quad, instances } => {
+                let instances = instances
+                    .per_instance()
+                    .map_err(|_| RoguelibError::draw("instancing is not supported"))?;
+                target.draw(
+                    (quad, instances),
+                    glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip),
+                    programs.instanced,
+                    uniforms,
+                    &parameters,
+                )
+            }
+            Storage::Quads { backgrounds, .. } if !text => target.draw(
+                backgrounds,
+                glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                programs.quads,
+                uniforms,
+                &parameters,
+            ),
+            Storage::Quads { text, .. } => target.draw(
+                text,
+                glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
+                programs.quads,
+                uniforms,
+                &parameters,
+            ),
+        };
+        drawn.map_err(RoguelibError::draw)
+    }
+
+    /// Rebuild the lines of the grid overlay when it changed
+    pub(crate) fn update_grid_lines(
+        &mut self,
+        display: &glium::Display,
+        overlay: Option<GridOverlay>,
+    ) -> Result<()> {
+        let current = self.grid_lines.as_ref().map(|lines| lines.overlay);
+        self.grid_lines = match overlay {
+            Some(overlay) if current != Some(overlay) => {
+                let (vertices, indices) =
+                    crate::backend::create_grid(&self.grid, &overlay, display)?;
+                Some(GridLines {
+                    overlay,
+                    vertices,
+                    indices,
+                })
+            }
+            Some(_) => self.grid_lines.take(),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Vertices and indices of the grid overlay
+    pub(crate) fn grid_lines(
+        &self,
+    ) -> Option<(&glium::VertexBuffer<Vertex>, &glium::IndexBuffer<u32>)> {
+        self.grid_lines
+            .as_ref()
+            .map(|lines| (&lines.vertices, &lines.indices))
+    }
+}
+
+/// Instance data of a cell, cells that are not drawn have empty rectangles
+fn cell_instance(grid: &LineGrid, changed: &ChangedCell) -> CellInstance {
+    let rect = |r: rusttype::Rect<f32>| [r.min.x, r.min.y, r.max.x, r.max.y];
+    let cell_rect = if grid.contains(changed.x, changed.y) {
+        rect(crate::vertex::gl_rect(
+            grid,
+            grid.cell_rect(changed.x, changed.y),
+        ))
+    } else {
+        [0.0; 4]
+    };
+    let (glyph_rect, uv_rect) = match changed.glyph {
+        Some(glyph) => (rect(glyph.gl_rect), rect(glyph.uv_rect)),
+        None => ([0.0; 4], [0.0; 4]),
+    };
+
+    CellInstance {
+        cell_rect,
+        glyph_rect,
+        uv_rect,
+        fg: changed.cell.fg.into(),
+        bg: changed.cell.bg.into(),
+    }
+}
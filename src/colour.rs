@@ -0,0 +1,139 @@
+/// An rgba colour with components in the range 0..1
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Colour {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Colour {
+    pub const BLACK: Colour = Colour::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Colour = Colour::rgb(1.0, 1.0, 1.0);
+    pub const GREY: Colour = Colour::rgb(0.5, 0.5, 0.5);
+    pub const DARK_GREY: Colour = Colour::rgb(0.25, 0.25, 0.25);
+    pub const RED: Colour = Colour::rgb(1.0, 0.0, 0.0);
+    pub const GREEN: Colour = Colour::rgb(0.0, 1.0, 0.0);
+    pub const BLUE: Colour = Colour::rgb(0.0, 0.0, 1.0);
+    pub const YELLOW: Colour = Colour::rgb(1.0, 1.0, 0.0);
+    pub const CYAN: Colour = Colour::rgb(0.0, 1.0, 1.0);
+    pub const MAGENTA: Colour = Colour::rgb(1.0, 0.0, 1.0);
+    pub const ORANGE: Colour = Colour::rgb(1.0, 0.5, 0.0);
+    pub const TRANSPARENT: Colour = Colour::rgba(0.0, 0.0, 0.0, 0.0);
+
+    /// Create an opaque colour
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Colour {
+        Colour { r, g, b, a: 1.0 }
+    }
+
+    /// Create a colour with an alpha component
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Colour {
+        Colour { r, g, b, a }
+    }
+
+    /// Create an opaque colour from 8 bit components
+    pub fn from_rgb8(r: u8, g: u8, b: u8) -> Colour {
+        Colour::from_rgba8(r, g, b, 255)
+    }
+
+    /// Create a colour from 8 bit components
+    pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Colour {
+        Colour::rgba(
+            f32::from(r) / 255.0,
+            f32::from(g) / 255.0,
+            f32::from(b) / 255.0,
+            f32::from(a) / 255.0,
+        )
+    }
+
+    /// Parse a colour in the form `#rrggbb` or `#rrggbbaa`, the `#` is optional
+    pub fn from_hex(hex: &str) -> Option<Colour> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        // The parser of the components also accepts signs
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) || (hex.len() != 6 && hex.len() != 8) {
+            return None;
+        }
+
+        let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        let alpha = if hex.len() == 8 { component(6)? } else { 255 };
+        Some(Colour::from_rgba8(
+            component(0)?,
+            component(2)?,
+            component(4)?,
+            alpha,
+        ))
+    }
+
+    /// Colour for one of the names of the colour constants in lower case, like `dark_grey`
+    pub fn from_name(name: &str) -> Option<Colour> {
+        let colour = match name {
+            "black" => Colour::BLACK,
+            "white" => Colour::WHITE,
+            "grey" | "gray" => Colour::GREY,
+            "dark_grey" | "dark_gray" => Colour::DARK_GREY,
+            "red" => Colour::RED,
+            "green" => Colour::GREEN,
+            "blue" => Colour::BLUE,
+            "yellow" => Colour::YELLOW,
+            "cyan" => Colour::CYAN,
+            "magenta" => Colour::MAGENTA,
+            "orange" => Colour::ORANGE,
+            "transparent" => Colour::TRANSPARENT,
+            _ => return None,
+        };
+        Some(colour)
+    }
+
+    /// Composite this colour over another colour using its alpha component
+    pub fn over(self, below: Colour) -> Colour {
+        let a = self.a + below.a * (1.0 - self.a);
+        if a <= 0.0 {
+            return Colour::TRANSPARENT;
+        }
+        let mix = |s: f32, b: f32| (s * self.a + b * below.a * (1.0 - self.a)) / a;
+        Colour::rgba(
+            mix(self.r, below.r),
+            mix(self.g, below.g),
+            mix(self.b, below.b),
+            a,
+        )
+    }
+
+    /// Same colour with a different alpha component
+    pub fn with_alpha(self, a: f32) -> Colour {
+        Colour { a, ..self }
+    }
+}
+
+impl From<Colour> for [f32; 4] {
+    fn from(c: Colour) -> [f32; 4] {
+        [c.r, c.g, c.b, c.a]
+    }
+}
+
+impl From<[f32; 4]> for Colour {
+    fn from(c: [f32; 4]) -> Colour {
+        Colour::rgba(c[0], c[1], c[2], c[3])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_colours() {
+        assert_eq!(Colour::from_hex("#ff0000"), Some(Colour::RED));
+        assert_eq!(Colour::from_hex("0000ff"), Some(Colour::BLUE));
+        assert_eq!(
+            Colour::from_hex("#FFFFFF80"),
+            Some(Colour::from_rgba8(255, 255, 255, 128))
+        );
+        assert_eq!(Colour::from_hex("#fffff"), None);
+        assert_eq!(Colour::from_hex("##ffffff"), None);
+        assert_eq!(Colour::from_hex("#+f+f+f"), None);
+        assert_eq!(Colour::from_hex("#-f-f-f-f"), None);
+        assert_eq!(Colour::from_hex("#ffffgg"), None);
+        assert_eq!(Colour::from_hex("#ffé0f"), None);
+    }
+}
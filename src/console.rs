@@ -0,0 +1,473 @@
+use crate::colour::Colour;
+use crate::frame::{Arms, LineStyle};
+use crate::rect::Rect;
+use crate::text::{Align, StyledChar, StyledText};
+use std::convert::TryFrom;
+
+/// A single cell of the console
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Cell {
+    /// Character displayed in the cell
+    pub glyph: char,
+    /// Foreground colour
+    pub fg: Colour,
+    /// Background colour
+    pub bg: Colour,
+}
+
+impl Cell {
+    /// Cell that does not cover anything when composited over another cell
+    pub const TRANSPARENT: Cell = Cell {
+        glyph: ' ',
+        fg: Colour::WHITE,
+        bg: Colour::TRANSPARENT,
+    };
+
+    /// Whether the cell draws a glyph
+    pub fn has_glyph(&self) -> bool {
+        !self.glyph.is_whitespace() && self.fg.a > 0.0
+    }
+
+    /// Composite this cell over another cell, the glyph of the cell below
+    /// stays visible through the background when this cell has no glyph
+    pub fn over(&self, below: &Cell) -> Cell {
+        if self.has_glyph() {
+            Cell {
+                glyph: self.glyph,
+                fg: self.fg,
+                bg: self.bg.over(below.bg),
+            }
+        } else {
+            Cell {
+                glyph: below.glyph,
+                fg: self
+                    .bg
+                    .over(below.fg.with_alpha(1.0))
+                    .with_alpha(below.fg.a),
+                bg: self.bg.over(below.bg),
+            }
+        }
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Cell {
+        Cell {
+            glyph: ' ',
+            fg: Colour::WHITE,
+            bg: Colour::BLACK,
+        }
+    }
+}
+
+/// A buffer of width x height cells that is handed to roguelib for rendering
+#[derive(Debug, Clone, PartialEq)]
+pub struct Console {
+    width: u32,
+    height: u32,
+    cells: Vec<Cell>,
+    /// Cell used when clearing the console
+    clear_cell: Cell,
+    /// Background colour of cells that are skipped when blitting
+    key_colour: Option<Colour>,
+}
+
+impl Console {
+    /// Create a new console filled with empty cells
+    pub fn new(width: u32, height: u32) -> Console {
+        Console::with_clear_cell(width, height, Cell::default())
+    }
+
+    /// Create a new console filled with transparent cells, used for layers that are
+    /// composited over other consoles
+    pub fn transparent(width: u32, height: u32) -> Console {
+        Console::with_clear_cell(width, height, Cell::TRANSPARENT)
+    }
+
+    /// Create a new console that is filled with the cell, also when it is cleared
+    pub fn with_clear_cell(width: u32, height: u32, clear_cell: Cell) -> Console {
+        Console {
+            width,
+            height,
+            cells: vec![clear_cell; width as usize * height as usize],
+            clear_cell,
+            key_colour: None,
+        }
+    }
+
+    /// Number of cells in the x direction
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Number of cells in the y direction
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Index into the cell buffer, None when outside of the console
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(cell_index(self.width, x, y))
+        } else {
+            None
+        }
+    }
+
+    /// Retrieve the cell at the specific position
+    pub fn get(&self, x: u32, y: u32) -> Option<&Cell> {
+        self.index(x, y).map(|i| &self.cells[i])
+    }
+
+    /// Retrieve the cell at the specific position for modification
+    pub fn get_mut(&mut self, x: u32, y: u32) -> Option<&mut Cell> {
+        match self.index(x, y) {
+            Some(i) => Some(&mut self.cells[i]),
+            None => None,
+        }
+    }
+
+    /// Set the cell at the specific position, positions outside of the console are ignored
+    pub fn set(&mut self, x: u32, y: u32, glyph: char, fg: Colour, bg: Colour) {
+        if let Some(cell) = self.get_mut(x, y) {
+            *cell = Cell { glyph, fg, bg };
+        }
+    }
+
+    /// Write text with its first character at the position, keeping the colours of the cells,
+    /// a newline continues below the position. Characters outside of the console are skipped
+    pub fn print(&mut self, x: u32, y: u32, text: &str) {
+        let chars: Vec<StyledChar> = text.chars().map(StyledChar::from).collect();
+        self.print_chars(x, y, &chars);
+    }
+
+    /// Write styled text like `print`, the cells take the colours of the text where it has them
+    pub fn print_styled(&mut self, x: u32, y: u32, text: &StyledText) {
+        self.print_chars(x, y, text.chars());
+    }
+
+    /// Write text wrapped at the width of the rectangle with every line aligned, keeping the
+    /// colours of the cells. Lines below the rectangle are skipped, returns the number of
+    /// lines the whole text needs
+    pub fn print_rect(&mut self, rect: Rect, text: &str, align: Align) -> u32 {
+        let chars: Vec<StyledChar> = text.chars().map(StyledChar::from).collect();
+        self.print_rect_chars(rect, &chars, align)
+    }
+
+    /// Write styled text like `print_rect`, the cells take the colours of the text where it
+    /// has them
+    pub fn print_rect_styled(&mut self, rect: Rect, text: &StyledText, align: Align) -> u32 {
+        self.print_rect_chars(rect, text.chars(), align)
+    }
+
+    fn print_chars(&mut self, x: u32, y: u32, chars: &[StyledChar]) {
+        for (row, line) in chars.split(|c| c.glyph == '\n').enumerate() {
+            // Carriage returns of windows line endings are not drawn
+            let line = line.iter().filter(|c| c.glyph != '\r');
+            for (column, c) in line.enumerate() {
+                let (x, y) = (
+                    x.saturating_add(column as u32),
+                    y.saturating_add(row as u32),
+                );
+                self.print_char(x, y, c);
+            }
+        }
+    }
+
+    fn print_rect_chars(&mut self, rect: Rect, chars: &[StyledChar], align: Align) -> u32 {
+        let glyphs: Vec<char> = chars.iter().map(|c| c.glyph).collect();
+        let lines = crate::text::wrap(&glyphs, rect.width);
+        for (row, line) in lines.iter().take(rect.height as usize).enumerate() {
+            let x = rect
+                .x
+                .saturating_add(align.offset(line.len() as u32, rect.width));
+            let y = rect.y.saturating_add(row as u32);
+            for (column, c) in chars[line.clone()].iter().enumerate() {
+                self.print_char(x.saturating_add(column as u32), y, c);
+            }
+        }
+        lines.len() as u32
+    }
+
+    /// Set the glyph of a cell and the colours the character has
+    fn print_char(&mut self, x: u32, y: u32, c: &StyledChar) {
+        if let Some(cell) = self.get_mut(x, y) {
+            cell.glyph = c.glyph;
+            if let Some(fg) = c.fg {
+                cell.fg = fg;
+            }
+            if let Some(bg) = c.bg {
+                cell.bg = bg;
+            }
+        }
+    }
+
+    /// Draw the outline of the rectangle, keeping the colours of the cells. Lines the outline
+    /// crosses or touches are joined, the title is centred on the top edge and cut off where it
+    /// does not fit between the corners
+    pub fn draw_frame(&mut self, rect: Rect, style: LineStyle, title: Option<&str>) {
+        if rect.width == 0 || rect.height == 0 {
+            return;
+        }
+        let right = rect.x.saturating_add(rect.width - 1);
+        let bottom = rect.y.saturating_add(rect.height - 1);
+        for (x, y) in rect.intersection(&self.rect()).cells() {
+            let vertical = x == rect.x || x == right;
+            let horizontal = y == rect.y || y == bottom;
+            if !vertical && !horizontal {
+                continue;
+            }
+            let arms = Arms {
+                up: vertical && y > rect.y,
+                right: horizontal && x < right,
+                down: vertical && y < bottom,
+                left: horizontal && x > rect.x,
+            };
+            self.draw_lines(x, y, arms, style);
+        }
+
+        if let Some(title) = title {
+            let inner = rect.width.saturating_sub(2);
+            let title: Vec<StyledChar> = title
+                .chars()
+                .take(inner as usize)
+                .map(StyledChar::from)
+                .collect();
+            let x = rect
+                .x
+                .saturating_add(1 + Align::Centre.offset(title.len() as u32, inner));
+            for (column, c) in title.iter().enumerate() {
+                self.print_char(x.saturating_add(column as u32), rect.y, c);
+            }
+        }
+    }
+
+    /// Draw a horizontal line from the position to the right, keeping the colours of the
+    /// cells. Lines it crosses or ends on are joined, like the sides of a frame it separates
+    pub fn draw_hline(&mut self, x: u32, y: u32, length: u32, style: LineStyle) {
+        for i in 0..length {
+            let arms = Arms {
+                right: i + 1 < length || length == 1,
+                left: i > 0 || length == 1,
+                ..Arms::default()
+            };
+            self.draw_lines(x.saturating_add(i), y, arms, style);
+        }
+    }
+
+    /// Draw a vertical line from the position downwards, keeping the colours of the cells.
+    /// Lines it crosses or ends on are joined, like the sides of a frame it separates
+    pub fn draw_vline(&mut self, x: u32, y: u32, length: u32, style: LineStyle) {
+        for i in 0..length {
+            let arms = Arms {
+                up: i > 0 || length == 1,
+                down: i + 1 < length || length == 1,
+                ..Arms::default()
+            };
+            self.draw_lines(x, y.saturating_add(i), arms, style);
+        }
+    }
+
+    /// Join lines into the glyph of a cell
+    fn draw_lines(&mut self, x: u32, y: u32, arms: Arms, style: LineStyle) {
+        if let Some(cell) = self.get_mut(x, y) {
+            cell.glyph = crate::frame::join(cell.glyph, arms, style);
+        }
+    }
+
+    /// Set all the cells in the rectangle to the given cell, cells outside of the console are
+    /// skipped
+    pub fn fill_rect(&mut self, rect: Rect, cell: Cell) {
+        for (x, y) in rect.intersection(&self.rect()).cells() {
+            if let Some(c) = self.get_mut(x, y) {
+                *c = cell;
+            }
+        }
+    }
+
+    /// Reset all the cells to the clear cell, the default cell unless the console was
+    /// created otherwise
+    pub fn clear(&mut self) {
+        self.fill(self.clear_cell);
+    }
+
+    /// Set all the cells to the given cell
+    pub fn fill(&mut self, cell: Cell) {
+        for c in self.cells.iter_mut() {
+            *c = cell;
+        }
+    }
+
+    /// Area covered by the console
+    pub fn rect(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    /// Treat cells with this background colour as transparent when blitting from this console
+    pub fn set_key_colour(&mut self, key_colour: Option<Colour>) {
+        self.key_colour = key_colour;
+    }
+
+    /// Copy a region of this console into another console with its top left corner at the
+    /// given position, the alpha values scale the foreground and background of the copied cells
+    /// which are then composited over the destination. Cells outside of either console are
+    /// skipped
+    pub fn blit(
+        &self,
+        region: Rect,
+        dest: &mut Console,
+        dest_x: i32,
+        dest_y: i32,
+        fg_alpha: f32,
+        bg_alpha: f32,
+    ) {
+        for (x, y) in region.intersection(&self.rect()).cells() {
+            let source = match self.get(x, y) {
+                Some(cell) => cell,
+                None => continue,
+            };
+            if Some(source.bg) == self.key_colour {
+                continue;
+            }
+
+            // Positions before the first or past the last cell are skipped
+            let target_x = u32::try_from(i64::from(dest_x) + i64::from(x - region.x));
+            let target_y = u32::try_from(i64::from(dest_y) + i64::from(y - region.y));
+            let (target_x, target_y) = match (target_x, target_y) {
+                (Ok(x), Ok(y)) => (x, y),
+                _ => continue,
+            };
+            if let Some(target) = dest.get_mut(target_x, target_y) {
+                let cell = Cell {
+                    glyph: source.glyph,
+                    fg: source.fg.with_alpha(source.fg.a * fg_alpha),
+                    bg: source.bg.with_alpha(source.bg.a * bg_alpha),
+                };
+                *target = cell.over(target);
+            }
+        }
+    }
+
+    /// Iterate over all cells together with their x and y position
+    pub fn cells(&self) -> impl Iterator<Item = (u32, u32, &Cell)> {
+        let width = self.width;
+        self.cells.iter().enumerate().map(move |(i, c)| {
+            let (x, y) = cell_position(width, i);
+            (x, y, c)
+        })
+    }
+}
+
+/// Index into the cell buffer of a console with this width, computed in usize so consoles
+/// with more than `u32::MAX` cells do not overflow
+fn cell_index(width: u32, x: u32, y: u32) -> usize {
+    y as usize * width as usize + x as usize
+}
+
+/// Position of an index into the cell buffer of a console with this width, the reverse of
+/// `cell_index`
+fn cell_position(width: u32, index: usize) -> (u32, u32) {
+    let width = width as usize;
+    ((index % width) as u32, (index / width) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn large_consoles_do_not_overflow() {
+        assert_eq!(cell_index(70_000, 69_999, 69_999), 4_899_999_999);
+        assert_eq!(cell_position(70_000, 4_899_999_999), (69_999, 69_999));
+        assert_eq!(cell_position(70_000, 4_294_967_296), (47_296, 61_356));
+        assert_eq!(cell_index(70_000, 47_296, 61_356), 4_294_967_296);
+    }
+
+    #[test]
+    fn cells_are_visited_row_by_row() {
+        let console = Console::new(3, 2);
+        let positions: Vec<_> = console.cells().map(|(x, y, _)| (x, y)).collect();
+        assert_eq!(positions, [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+        assert_eq!(console.index(2, 1), Some(5));
+        assert_eq!(console.index(3, 0), None);
+    }
+
+    /// Console of 2x2 cells with the glyphs a to d on a red background
+    fn source() -> Console {
+        let mut console = Console::new(2, 2);
+        for (i, (x, y)) in console.rect().cells().enumerate() {
+            let glyph = (b'a' + i as u8) as char;
+            console.set(x, y, glyph, Colour::WHITE, Colour::RED);
+        }
+        console
+    }
+
+    /// Glyphs of a console row by row
+    fn glyphs(console: &Console) -> Vec<String> {
+        (0..console.height())
+            .map(|y| {
+                (0..console.width())
+                    .map(|x| console.get(x, y).unwrap().glyph)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fill_rect_past_the_largest_position() {
+        let mut console = Console::new(4, 3);
+        let cell = Cell {
+            glyph: '#',
+            ..Cell::default()
+        };
+        console.fill_rect(Rect::new(2, 1, u32::MAX, u32::MAX), cell);
+        assert_eq!(glyphs(&console), ["    ", "  ##", "  ##"]);
+        console.fill_rect(Rect::new(u32::MAX, u32::MAX, u32::MAX, u32::MAX), cell);
+        assert_eq!(glyphs(&console), ["    ", "  ##", "  ##"]);
+    }
+
+    #[test]
+    fn draw_frame_past_the_largest_position() {
+        let mut console = Console::new(4, 3);
+        console.draw_frame(Rect::new(1, 1, u32::MAX, u32::MAX), LineStyle::Single, None);
+        assert_eq!(glyphs(&console), ["    ", " ┌──", " │  "]);
+        console.draw_frame(
+            Rect::new(u32::MAX - 1, 0, u32::MAX, u32::MAX),
+            LineStyle::Single,
+            Some("title"),
+        );
+        assert_eq!(glyphs(&console), ["    ", " ┌──", " │  "]);
+    }
+
+    #[test]
+    fn blit_whole_region() {
+        let mut dest = Console::new(3, 3);
+        let region = Rect::new(0, 0, u32::MAX, u32::MAX);
+        source().blit(region, &mut dest, 1, 1, 1.0, 1.0);
+        assert_eq!(glyphs(&dest), ["   ", " ab", " cd"]);
+        assert_eq!(dest.get(1, 1).unwrap().bg, Colour::RED);
+        assert_eq!(dest.get(0, 0).unwrap().bg, Colour::BLACK);
+    }
+
+    #[test]
+    fn blit_to_negative_positions() {
+        let mut dest = Console::new(3, 3);
+        source().blit(source().rect(), &mut dest, -1, -1, 1.0, 1.0);
+        assert_eq!(glyphs(&dest), ["d  ", "   ", "   "]);
+        let mut dest = Console::new(3, 3);
+        source().blit(source().rect(), &mut dest, i32::MIN, 2, 1.0, 1.0);
+        assert_eq!(glyphs(&dest), ["   ", "   ", "   "]);
+    }
+
+    #[test]
+    fn blit_skips_the_key_colour() {
+        let mut source = source();
+        source.set(1, 0, 'b', Colour::WHITE, Colour::BLUE);
+        source.set_key_colour(Some(Colour::BLUE));
+        let mut dest = Console::new(2, 2);
+        source.blit(source.rect(), &mut dest, 0, 0, 1.0, 1.0);
+        assert_eq!(glyphs(&dest), ["a ", "cd"]);
+        assert_eq!(dest.get(1, 0).unwrap().bg, Colour::BLACK);
+    }
+}
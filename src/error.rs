@@ -0,0 +1,91 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Errors that can occur while using the roguelib library
+#[derive(Debug)]
+pub enum RoguelibError {
+    /// The window, terminal or graphics context could not be created
+    Context(String),
+    /// A file could not be read or written
+    Io(PathBuf, io::Error),
+    /// The font data could not be parsed
+    FontParse(rusttype::Error),
+    /// The tileset image could not be decoded or split into tiles
+    TilesetParse(String),
+    /// No font has been added with this name
+    MissingFont(String),
+    /// The font has no usable glyph for this character
+    MissingGlyph(char),
+    /// A font or tileset scale that is not a positive number
+    InvalidScale(f32),
+    /// The glyphs of a single frame do not fit in the glyph atlas, even at its largest size
+    CacheOverflow,
+    /// Rendering or presenting a frame failed
+    Draw(String),
+    /// A tag in styled text is malformed or a bracket is not escaped
+    Markup(String),
+    /// Lines of a key bindings file that are malformed or conflict, with their line numbers
+    Bindings(Vec<String>),
+    /// The backend was asked to wait for input that can never arrive
+    NoEvents,
+}
+
+/// Result type used throughout the roguelib library
+pub type Result<T> = std::result::Result<T, RoguelibError>;
+
+impl fmt::Display for RoguelibError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoguelibError::Context(e) => write!(f, "could not create context: {}", e),
+            RoguelibError::Io(path, e) => {
+                write!(f, "could not access '{}': {}", path.display(), e)
+            }
+            RoguelibError::FontParse(e) => write!(f, "could not parse font: {}", e),
+            RoguelibError::TilesetParse(e) => write!(f, "could not load tileset: {}", e),
+            RoguelibError::MissingFont(name) => write!(f, "font '{}' does not exist", name),
+            RoguelibError::MissingGlyph(c) => write!(f, "font has no glyph for '{}'", c),
+            RoguelibError::InvalidScale(scale) => {
+                write!(f, "scale {} is not a positive number", scale)
+            }
+            RoguelibError::CacheOverflow => write!(f, "glyphs do not fit in the glyph cache"),
+            RoguelibError::Draw(e) => write!(f, "could not draw frame: {}", e),
+            RoguelibError::Markup(e) => write!(f, "could not parse markup: {}", e),
+            RoguelibError::Bindings(errors) => {
+                write!(f, "invalid key bindings: {}", errors.join("; "))
+            }
+            RoguelibError::NoEvents => write!(f, "no input left to wait for"),
+        }
+    }
+}
+
+impl Error for RoguelibError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RoguelibError::Io(_, e) => Some(e),
+            RoguelibError::FontParse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// Only the window and terminal backends wrap errors of their own
+#[cfg_attr(not(any(feature = "opengl", feature = "terminal")), allow(dead_code))]
+impl RoguelibError {
+    /// Wrap a context creation error from one of the backends
+    pub(crate) fn context<E: fmt::Display>(e: E) -> RoguelibError {
+        RoguelibError::Context(e.to_string())
+    }
+
+    /// Wrap a drawing error from one of the backends
+    pub(crate) fn draw<E: fmt::Display>(e: E) -> RoguelibError {
+        RoguelibError::Draw(e.to_string())
+    }
+}
+
+impl From<rusttype::Error> for RoguelibError {
+    fn from(e: rusttype::Error) -> RoguelibError {
+        RoguelibError::FontParse(e)
+    }
+}
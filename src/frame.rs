@@ -0,0 +1,223 @@
+/// Line style of frames and separators
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineStyle {
+    Single,
+    Double,
+    Heavy,
+    /// Single lines with rounded corners
+    Rounded,
+}
+
+impl LineStyle {
+    fn weight(self) -> Weight {
+        match self {
+            LineStyle::Single | LineStyle::Rounded => Weight::Light,
+            LineStyle::Double => Weight::Double,
+            LineStyle::Heavy => Weight::Heavy,
+        }
+    }
+}
+
+/// Weight of a single arm of a box drawing character
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Weight {
+    None,
+    Light,
+    Heavy,
+    Double,
+}
+
+/// Which arms of a box drawing character are drawn
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub(crate) struct Arms {
+    pub up: bool,
+    pub right: bool,
+    pub down: bool,
+    pub left: bool,
+}
+
+/// Box drawing characters with their arms written as up, right, down and left, where `.` is
+/// no arm, `l` a light arm, `h` a heavy arm and `d` a double arm. Characters earlier in the
+/// table are preferred when characters have the same arms
+const BOX_CHARS: [(char, &str); 55] = [
+    ('─', ".l.l"),
+    ('│', "l.l."),
+    ('┌', ".ll."),
+    ('┐', "..ll"),
+    ('└', "ll.."),
+    ('┘', "l..l"),
+    ('├', "lll."),
+    ('┤', "l.ll"),
+    ('┬', ".lll"),
+    ('┴', "ll.l"),
+    ('┼', "llll"),
+    ('╭', ".ll."),
+    ('╮', "..ll"),
+    ('╰', "ll.."),
+    ('╯', "l..l"),
+    ('━', ".h.h"),
+    ('┃', "h.h."),
+    ('┏', ".hh."),
+    ('┓', "..hh"),
+    ('┗', "hh.."),
+    ('┛', "h..h"),
+    ('┣', "hhh."),
+    ('┫', "h.hh"),
+    ('┳', ".hhh"),
+    ('┻', "hh.h"),
+    ('╋', "hhhh"),
+    ('═', ".d.d"),
+    ('║', "d.d."),
+    ('╔', ".dd."),
+    ('╗', "..dd"),
+    ('╚', "dd.."),
+    ('╝', "d..d"),
+    ('╠', "ddd."),
+    ('╣', "d.dd"),
+    ('╦', ".ddd"),
+    ('╩', "dd.d"),
+    ('╬', "dddd"),
+    ('╒', ".dl."),
+    ('╓', ".ld."),
+    ('╕', "..ld"),
+    ('╖', "..dl"),
+    ('╘', "ld.."),
+    ('╙', "dl.."),
+    ('╛', "l..d"),
+    ('╜', "d..l"),
+    ('╞', "ldl."),
+    ('╟', "dld."),
+    ('╡', "l.ld"),
+    ('╢', "d.dl"),
+    ('╤', ".dld"),
+    ('╥', ".ldl"),
+    ('╧', "ld.d"),
+    ('╨', "dl.l"),
+    ('╪', "ldld"),
+    ('╫', "dldl"),
+];
+
+/// Weights of the arms of a table entry
+fn parse_weights(arms: &str) -> [Weight; 4] {
+    let mut weights = [Weight::None; 4];
+    for (weight, c) in weights.iter_mut().zip(arms.chars()) {
+        *weight = match c {
+            'l' => Weight::Light,
+            'h' => Weight::Heavy,
+            'd' => Weight::Double,
+            _ => Weight::None,
+        };
+    }
+    weights
+}
+
+/// Weights of the arms of a glyph, all arms are empty for glyphs that are not box drawing
+/// characters
+fn weights_of(glyph: char) -> [Weight; 4] {
+    BOX_CHARS
+        .iter()
+        .find(|(c, _)| *c == glyph)
+        .map(|(_, arms)| parse_weights(arms))
+        .unwrap_or([Weight::None; 4])
+}
+
+/// Glyph for the weights of the arms, if there is one
+fn glyph_for(weights: [Weight; 4]) -> Option<char> {
+    BOX_CHARS
+        .iter()
+        .find(|(_, arms)| parse_weights(arms) == weights)
+        .map(|(c, _)| *c)
+}
+
+/// Glyph for lines in a style drawn over an existing glyph, lines that already run through the
+/// cell are joined with a junction character
+pub(crate) fn join(existing: char, arms: Arms, style: LineStyle) -> char {
+    let weight = style.weight();
+    let new = [arms.up, arms.right, arms.down, arms.left];
+    let mut weights = weights_of(existing);
+    for (w, new) in weights.iter_mut().zip(new.iter()) {
+        if *new {
+            *w = weight;
+        }
+    }
+
+    // A single arm is drawn as a full line
+    let [up, right, down, left] = weights;
+    let weights = match weights.iter().filter(|w| **w != Weight::None).count() {
+        1 if up != Weight::None || down != Weight::None => {
+            let w = if up != Weight::None { up } else { down };
+            [w, Weight::None, w, Weight::None]
+        }
+        1 => {
+            let w = if left != Weight::None { left } else { right };
+            [Weight::None, w, Weight::None, w]
+        }
+        _ => weights,
+    };
+
+    // Combinations without a character, like heavy with double, take the new style
+    let uniform = weights.map(|w| if w == Weight::None { w } else { weight });
+    let glyph = glyph_for(weights)
+        .or_else(|| glyph_for(uniform))
+        .unwrap_or(existing);
+
+    if style == LineStyle::Rounded {
+        round(glyph)
+    } else {
+        glyph
+    }
+}
+
+/// Rounded version of a single corner
+fn round(glyph: char) -> char {
+    match glyph {
+        '┌' => '╭',
+        '┐' => '╮',
+        '└' => '╰',
+        '┘' => '╯',
+        c => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Arms from a string of up, right, down and left where `x` is an arm
+    fn arms(arms: &str) -> Arms {
+        let arm = |i: usize| arms.as_bytes()[i] == b'x';
+        Arms {
+            up: arm(0),
+            right: arm(1),
+            down: arm(2),
+            left: arm(3),
+        }
+    }
+
+    #[test]
+    fn lines_on_empty_cells() {
+        assert_eq!(join(' ', arms(".xx."), LineStyle::Single), '┌');
+        assert_eq!(join(' ', arms(".xx."), LineStyle::Rounded), '╭');
+        assert_eq!(join('a', arms("..xx"), LineStyle::Double), '╗');
+        assert_eq!(join(' ', arms("xxxx"), LineStyle::Heavy), '╋');
+        // A single arm is drawn as a full line
+        assert_eq!(join(' ', arms("x..."), LineStyle::Single), '│');
+        assert_eq!(join(' ', arms("...x"), LineStyle::Double), '═');
+    }
+
+    #[test]
+    fn lines_join_existing_lines() {
+        assert_eq!(join('│', arms(".x.x"), LineStyle::Single), '┼');
+        assert_eq!(join('─', arms("x.x."), LineStyle::Single), '┼');
+        assert_eq!(join('┐', arms("x..."), LineStyle::Single), '┤');
+        assert_eq!(join('║', arms(".x.."), LineStyle::Single), '╟');
+        assert_eq!(join('═', arms("..x."), LineStyle::Single), '╤');
+        // Rounded corners join like single lines
+        assert_eq!(join('╭', arms("...x"), LineStyle::Single), '┬');
+        // Heavy and double lines have no junctions, the new style is used
+        assert_eq!(join('║', arms(".x.x"), LineStyle::Heavy), '╋');
+        // Without arms the glyph is kept
+        assert_eq!(join('a', Arms::default(), LineStyle::Single), 'a');
+        assert_eq!(join('╪', Arms::default(), LineStyle::Double), '╪');
+    }
+}
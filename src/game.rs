@@ -0,0 +1,181 @@
+use crate::console::Console;
+use crate::input::InputState;
+use std::time::Duration;
+
+/// How `Roguelib::run` decides when to update and draw the game
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum RunMode {
+    /// Update every timestep and draw after every update
+    #[default]
+    Realtime,
+    /// Sleep until there is input and update once for it, the console is only drawn when
+    /// it changed or a redraw was requested. While an animation is active the game is
+    /// updated every timestep
+    TurnBased,
+}
+
+/// What the game loop does with the state stack after an update
+pub enum Transition {
+    /// Keep updating the current state
+    Continue,
+    /// Pause the current state and start a new one on top of it, like a menu over the map
+    Push(Box<dyn GameState>),
+    /// Stop the current state and resume the one below it, the game ends after the last state
+    Pop,
+    /// Stop the current state and start a new one in its place
+    Switch(Box<dyn GameState>),
+    /// Stop every state and end the game
+    Quit,
+}
+
+/// A state of the game that is run by `Roguelib::run`, only the state on top of the stack is
+/// updated and rendered
+pub trait GameState {
+    /// Advance the state by one timestep, or by the input that arrived in turn based mode.
+    /// Input is seen by the first update after it happened
+    fn update(&mut self, ctx: &mut Context, input: &InputState) -> Transition;
+
+    /// Draw the state into a cleared console that covers the screen
+    fn render(&mut self, console: &mut Console);
+
+    /// Called when the state is started, before its first update
+    fn on_start(&mut self, _ctx: &mut Context) {}
+
+    /// Called when the state is stopped, also when the game ends or the window is closed
+    fn on_stop(&mut self, _ctx: &mut Context) {}
+}
+
+/// Timing and screen information for game states
+#[derive(Debug, Clone)]
+pub struct Context {
+    timestep: Duration,
+    ticks: u64,
+    width: u32,
+    height: u32,
+    redraw: bool,
+    animating: bool,
+}
+
+impl Context {
+    pub(crate) fn new(timestep: Duration, width: u32, height: u32) -> Context {
+        Context {
+            timestep,
+            ticks: 0,
+            width,
+            height,
+            redraw: false,
+            animating: false,
+        }
+    }
+
+    /// Game time that passes with every update
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    /// Number of updates since the game started
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Number of columns and rows of the console the states render into
+    pub fn console_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Draw the console after the next render, even when it did not change
+    pub fn request_redraw(&mut self) {
+        self.redraw = true;
+    }
+
+    /// Keep updating every timestep while an animation is active in turn based mode,
+    /// instead of waiting for input
+    pub fn set_animating(&mut self, animating: bool) {
+        self.animating = animating;
+    }
+
+    /// Whether an animation is active
+    pub fn animating(&self) -> bool {
+        self.animating
+    }
+
+    /// Whether a redraw was requested
+    pub(crate) fn redraw_requested(&self) -> bool {
+        self.redraw
+    }
+
+    /// Forget the redraw request after drawing
+    pub(crate) fn take_redraw(&mut self) -> bool {
+        std::mem::replace(&mut self.redraw, false)
+    }
+
+    /// The console was recreated for a new screen size
+    pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+}
+
+/// The stack of game states run by the game loop
+pub(crate) struct StateStack {
+    states: Vec<Box<dyn GameState>>,
+}
+
+impl StateStack {
+    /// Start the first state
+    pub(crate) fn new(mut state: Box<dyn GameState>, ctx: &mut Context) -> StateStack {
+        state.on_start(ctx);
+        StateStack {
+            states: vec![state],
+        }
+    }
+
+    /// Whether the game has ended
+    pub(crate) fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Update the state on top and apply its transition
+    pub(crate) fn update(&mut self, ctx: &mut Context, input: &InputState) {
+        let transition = match self.states.last_mut() {
+            Some(state) => state.update(ctx, input),
+            None => return,
+        };
+        ctx.ticks += 1;
+
+        match transition {
+            Transition::Continue => {}
+            Transition::Push(mut state) => {
+                state.on_start(ctx);
+                self.states.push(state);
+            }
+            Transition::Pop => {
+                if let Some(mut state) = self.states.pop() {
+                    state.on_stop(ctx);
+                }
+            }
+            Transition::Switch(mut state) => {
+                if let Some(mut current) = self.states.pop() {
+                    current.on_stop(ctx);
+                }
+                state.on_start(ctx);
+                self.states.push(state);
+            }
+            Transition::Quit => self.stop(ctx),
+        }
+    }
+
+    /// Render the state on top
+    pub(crate) fn render(&mut self, console: &mut Console) {
+        if let Some(state) = self.states.last_mut() {
+            state.render(console);
+        }
+    }
+
+    /// Stop every state, from the top of the stack down
+    pub(crate) fn stop(&mut self, ctx: &mut Context) {
+        while let Some(mut state) = self.states.pop() {
+            state.on_stop(ctx);
+        }
+    }
+}
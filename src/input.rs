@@ -0,0 +1,612 @@
+use crate::util::LineGrid;
+use rusttype::Vector;
+use std::collections::{HashMap, HashSet};
+
+/// Input delivered by a backend
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Event {
+    /// The window or terminal has been asked to close
+    CloseRequested,
+    /// The screen has been resized to the given physical dimensions
+    Resized { width: u32, height: u32 },
+    /// A key has been pressed or released, `repeat` is set for presses of a key that is held
+    /// down and repeated by the system
+    Key {
+        key: Key,
+        pressed: bool,
+        repeat: bool,
+        modifiers: Modifiers,
+    },
+    /// A character has been typed, with the keyboard layout and modifiers applied
+    Char(char),
+    /// The window gained or lost the keyboard focus
+    Focused(bool),
+    /// The contents of the screen were lost and have to be drawn again
+    Refresh,
+    /// The mouse moved over a cell of the grid, reported for every movement
+    MouseMoved { x: u32, y: u32 },
+    /// The mouse moved onto a cell of the grid
+    CellEntered { x: u32, y: u32 },
+    /// The mouse moved off a cell of the grid, onto another cell or off the grid
+    CellLeft { x: u32, y: u32 },
+    /// A mouse button has been pressed or released over a cell, or off the grid
+    MouseButton {
+        button: MouseButton,
+        pressed: bool,
+        cell: Option<(u32, u32)>,
+    },
+    /// The mouse wheel turned by a number of lines over a cell, or off the grid.
+    /// Positive lines scroll up, away from the user
+    MouseWheel {
+        lines: f32,
+        cell: Option<(u32, u32)>,
+    },
+}
+
+/// A button of the mouse
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u8),
+}
+
+/// Modifier keys that were held down during a key event
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The windows, command or super key
+    pub logo: bool,
+}
+
+impl Modifiers {
+    /// No modifiers held down
+    pub const NONE: Modifiers = Modifiers {
+        shift: false,
+        ctrl: false,
+        alt: false,
+        logo: false,
+    };
+}
+
+/// A key on the keyboard, independent of the backend that reported it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    Escape,
+    Enter,
+    Space,
+    Tab,
+    Backspace,
+    Comma,
+    Period,
+    Slash,
+    Semicolon,
+    Minus,
+    Equals,
+    /// Either shift key
+    Shift,
+    /// Either control key
+    Ctrl,
+    /// Either alt key
+    Alt,
+    /// Either windows, command or super key
+    Logo,
+}
+
+/// Names of the keys in binding files
+const KEY_NAMES: [(Key, &str); 83] = [
+    (Key::A, "a"),
+    (Key::B, "b"),
+    (Key::C, "c"),
+    (Key::D, "d"),
+    (Key::E, "e"),
+    (Key::F, "f"),
+    (Key::G, "g"),
+    (Key::H, "h"),
+    (Key::I, "i"),
+    (Key::J, "j"),
+    (Key::K, "k"),
+    (Key::L, "l"),
+    (Key::M, "m"),
+    (Key::N, "n"),
+    (Key::O, "o"),
+    (Key::P, "p"),
+    (Key::Q, "q"),
+    (Key::R, "r"),
+    (Key::S, "s"),
+    (Key::T, "t"),
+    (Key::U, "u"),
+    (Key::V, "v"),
+    (Key::W, "w"),
+    (Key::X, "x"),
+    (Key::Y, "y"),
+    (Key::Z, "z"),
+    (Key::Key0, "0"),
+    (Key::Key1, "1"),
+    (Key::Key2, "2"),
+    (Key::Key3, "3"),
+    (Key::Key4, "4"),
+    (Key::Key5, "5"),
+    (Key::Key6, "6"),
+    (Key::Key7, "7"),
+    (Key::Key8, "8"),
+    (Key::Key9, "9"),
+    (Key::Numpad0, "numpad0"),
+    (Key::Numpad1, "numpad1"),
+    (Key::Numpad2, "numpad2"),
+    (Key::Numpad3, "numpad3"),
+    (Key::Numpad4, "numpad4"),
+    (Key::Numpad5, "numpad5"),
+    (Key::Numpad6, "numpad6"),
+    (Key::Numpad7, "numpad7"),
+    (Key::Numpad8, "numpad8"),
+    (Key::Numpad9, "numpad9"),
+    (Key::F1, "f1"),
+    (Key::F2, "f2"),
+    (Key::F3, "f3"),
+    (Key::F4, "f4"),
+    (Key::F5, "f5"),
+    (Key::F6, "f6"),
+    (Key::F7, "f7"),
+    (Key::F8, "f8"),
+    (Key::F9, "f9"),
+    (Key::F10, "f10"),
+    (Key::F11, "f11"),
+    (Key::F12, "f12"),
+    (Key::Left, "left"),
+    (Key::Right, "right"),
+    (Key::Up, "up"),
+    (Key::Down, "down"),
+    (Key::Home, "home"),
+    (Key::End, "end"),
+    (Key::PageUp, "pageup"),
+    (Key::PageDown, "pagedown"),
+    (Key::Insert, "insert"),
+    (Key::Delete, "delete"),
+    (Key::Escape, "escape"),
+    (Key::Enter, "enter"),
+    (Key::Space, "space"),
+    (Key::Tab, "tab"),
+    (Key::Backspace, "backspace"),
+    (Key::Comma, "comma"),
+    (Key::Period, "period"),
+    (Key::Slash, "slash"),
+    (Key::Semicolon, "semicolon"),
+    (Key::Minus, "minus"),
+    (Key::Equals, "equals"),
+    (Key::Shift, "shift"),
+    (Key::Ctrl, "ctrl"),
+    (Key::Alt, "alt"),
+    (Key::Logo, "logo"),
+];
+
+impl Key {
+    /// Key for a letter or digit, letters are case insensitive
+    pub fn from_char(c: char) -> Option<Key> {
+        use self::Key::*;
+        const LETTERS: [Key; 26] = [
+            A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+        ];
+        const DIGITS: [Key; 10] = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+
+        let key = match c.to_ascii_lowercase() {
+            c @ 'a'..='z' => LETTERS[(c as u8 - b'a') as usize],
+            c @ '0'..='9' => DIGITS[(c as u8 - b'0') as usize],
+            ' ' => Space,
+            '\t' => Tab,
+            ',' => Comma,
+            '.' => Period,
+            '/' => Slash,
+            ';' => Semicolon,
+            '-' => Minus,
+            '=' => Equals,
+            _ => return None,
+        };
+        Some(key)
+    }
+
+    /// Name of the key in binding files, like `a`, `5`, `numpad8` or `pageup`
+    pub fn name(self) -> &'static str {
+        KEY_NAMES
+            .iter()
+            .find(|(key, _)| *key == self)
+            .map(|(_, name)| *name)
+            .unwrap_or("")
+    }
+
+    /// Key with a name in binding files, names are case insensitive
+    pub fn from_name(name: &str) -> Option<Key> {
+        KEY_NAMES
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name))
+            .map(|(key, _)| *key)
+    }
+}
+
+/// State of the keyboard for a single frame, built from the events since the previous frame
+#[derive(Debug, Clone, Default)]
+pub struct InputState {
+    held: HashSet<Key>,
+    pressed: HashSet<Key>,
+    repeated: HashSet<Key>,
+    released: HashSet<Key>,
+    /// Modifiers held down at the end of the frame
+    modifiers: Modifiers,
+    /// Modifiers held down when the keys that went down during the frame or are held were
+    /// last pressed or repeated
+    key_modifiers: HashMap<Key, Modifiers>,
+    mouse_cell: Option<(u32, u32)>,
+    buttons_held: HashSet<MouseButton>,
+    /// Buttons pressed during the frame, with the cell they were pressed on
+    buttons_pressed: Vec<(MouseButton, Option<(u32, u32)>)>,
+    buttons_released: HashSet<MouseButton>,
+    wheel: f32,
+    text: String,
+    close_requested: bool,
+    resized: Option<(u32, u32)>,
+    refresh: bool,
+}
+
+impl InputState {
+    /// Create the state of a frame without input
+    pub fn new() -> InputState {
+        InputState::default()
+    }
+
+    /// Forget the input of the previous frame, keys that are held down stay held
+    pub fn begin_frame(&mut self) {
+        self.pressed.clear();
+        self.repeated.clear();
+        self.released.clear();
+        let held = &self.held;
+        self.key_modifiers.retain(|key, _| held.contains(key));
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.wheel = 0.0;
+        self.text.clear();
+        self.close_requested = false;
+        self.resized = None;
+        self.refresh = false;
+    }
+
+    /// Add an event to the state of the frame
+    pub fn handle(&mut self, event: &Event) {
+        match *event {
+            Event::CloseRequested => self.close_requested = true,
+            Event::Resized { width, height } => self.resized = Some((width, height)),
+            Event::Refresh => self.refresh = true,
+            Event::Key {
+                key,
+                pressed,
+                repeat,
+                modifiers,
+            } => {
+                self.modifiers = modifiers;
+                // Systems differ in whether the event of a modifier key includes the key itself
+                let modifier = match key {
+                    Key::Shift => Some(&mut self.modifiers.shift),
+                    Key::Ctrl => Some(&mut self.modifiers.ctrl),
+                    Key::Alt => Some(&mut self.modifiers.alt),
+                    Key::Logo => Some(&mut self.modifiers.logo),
+                    _ => None,
+                };
+                if let Some(held) = modifier {
+                    *held = pressed;
+                }
+                if pressed {
+                    if !repeat {
+                        self.pressed.insert(key);
+                    }
+                    self.repeated.insert(key);
+                    self.held.insert(key);
+                    self.key_modifiers.insert(key, modifiers);
+                } else {
+                    self.released.insert(key);
+                    self.held.remove(&key);
+                }
+            }
+            Event::Char(c) => self.text.push(c),
+            // Releases are not reported for keys that are let go without focus
+            Event::Focused(false) => {
+                self.released.extend(self.held.drain());
+                self.buttons_released.extend(self.buttons_held.drain());
+                self.modifiers = Modifiers::NONE;
+            }
+            Event::Focused(true) => {}
+            Event::MouseMoved { x, y } | Event::CellEntered { x, y } => {
+                self.mouse_cell = Some((x, y))
+            }
+            Event::CellLeft { x, y } => {
+                if self.mouse_cell == Some((x, y)) {
+                    self.mouse_cell = None;
+                }
+            }
+            Event::MouseButton {
+                button,
+                pressed,
+                cell,
+            } => {
+                if pressed {
+                    self.buttons_pressed.push((button, cell));
+                    self.buttons_held.insert(button);
+                } else {
+                    self.buttons_released.insert(button);
+                    self.buttons_held.remove(&button);
+                }
+            }
+            Event::MouseWheel { lines, .. } => self.wheel += lines,
+        }
+    }
+
+    /// Whether the key went down during the frame, repeats of a held key do not count
+    pub fn is_pressed(&self, key: Key) -> bool {
+        self.pressed.contains(&key)
+    }
+
+    /// Whether the key went down or was repeated during the frame, for input like moving
+    /// through a menu that should follow the key repeat of the system
+    pub fn is_repeated(&self, key: Key) -> bool {
+        self.repeated.contains(&key)
+    }
+
+    /// Whether the key is down at the end of the frame
+    pub fn is_held(&self, key: Key) -> bool {
+        self.held.contains(&key)
+    }
+
+    /// Whether the key went up during the frame
+    pub fn is_released(&self, key: Key) -> bool {
+        self.released.contains(&key)
+    }
+
+    /// Modifiers held down at the end of the frame
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Modifiers held down when the key was last pressed or repeated, None when the key did not
+    /// go down during the frame and is not held
+    pub fn key_modifiers(&self, key: Key) -> Option<Modifiers> {
+        self.key_modifiers.get(&key).copied()
+    }
+
+    /// Cell under the mouse at the end of the frame, None when the mouse is off the grid
+    pub fn mouse_cell(&self) -> Option<(u32, u32)> {
+        self.mouse_cell
+    }
+
+    /// Whether the mouse button went down during the frame
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.iter().any(|(b, _)| *b == button)
+    }
+
+    /// Whether the mouse button is down at the end of the frame
+    pub fn is_mouse_held(&self, button: MouseButton) -> bool {
+        self.buttons_held.contains(&button)
+    }
+
+    /// Whether the mouse button went up during the frame
+    pub fn is_mouse_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// Cell the mouse button was last pressed on during the frame, None when it was not
+    /// pressed or pressed off the grid
+    pub fn clicked(&self, button: MouseButton) -> Option<(u32, u32)> {
+        self.buttons_pressed
+            .iter()
+            .rev()
+            .find(|(b, _)| *b == button)
+            .and_then(|(_, cell)| *cell)
+    }
+
+    /// Lines the mouse wheel turned during the frame, positive lines scroll up
+    pub fn wheel(&self) -> f32 {
+        self.wheel
+    }
+
+    /// Characters typed during the frame
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether the window or terminal was asked to close during the frame
+    pub fn close_requested(&self) -> bool {
+        self.close_requested
+    }
+
+    /// Whether the contents of the screen were lost during the frame and have to be drawn again
+    pub fn refresh_requested(&self) -> bool {
+        self.refresh
+    }
+
+    /// The last physical dimensions the screen was resized to during the frame
+    pub fn resized(&self) -> Option<(u32, u32)> {
+        self.resized
+    }
+}
+
+/// Cell under the mouse cursor of a backend, turns cursor positions into cell events
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MouseTracker {
+    /// Grid of the last presented frame
+    grid: Option<LineGrid>,
+    /// Position of the cursor in physical pixels, None when it is outside of the screen
+    position: Option<Vector<f32>>,
+    /// Cell the last cell event was reported for
+    hovered: Option<(u32, u32)>,
+}
+
+impl MouseTracker {
+    /// Use the grid of a presented frame for the cursor positions that follow
+    pub(crate) fn set_grid(&mut self, grid: LineGrid) {
+        self.grid = Some(grid);
+    }
+
+    /// Cell under the cursor in the current grid
+    pub(crate) fn cell(&self) -> Option<(u32, u32)> {
+        self.grid.as_ref()?.cell_at(self.position?)
+    }
+
+    /// Height of a line of the current grid in pixels, used to turn scrolled pixels into lines
+    #[cfg(feature = "opengl")]
+    pub(crate) fn line_height(&self) -> Option<f32> {
+        self.grid
+            .as_ref()
+            .map(|grid| grid.cell_rect(0, 0).height())
+            .filter(|height| *height > 0.0)
+    }
+
+    /// The cursor moved to a position in physical pixels
+    pub(crate) fn moved(&mut self, position: Vector<f32>, callback: &mut dyn FnMut(Event)) {
+        self.position = Some(position);
+        let cell = self.cell();
+        self.hover(cell, callback);
+        if let Some((x, y)) = cell {
+            callback(Event::MouseMoved { x, y });
+        }
+    }
+
+    /// The cursor left the screen
+    #[cfg(feature = "opengl")]
+    pub(crate) fn left(&mut self, callback: &mut dyn FnMut(Event)) {
+        self.position = None;
+        self.hover(None, callback);
+    }
+
+    /// Report leaving the hovered cell and entering the new one when they differ
+    fn hover(&mut self, cell: Option<(u32, u32)>, callback: &mut dyn FnMut(Event)) {
+        if cell == self.hovered {
+            return;
+        }
+        if let Some((x, y)) = self.hovered {
+            callback(Event::CellLeft { x, y });
+        }
+        if let Some((x, y)) = cell {
+            callback(Event::CellEntered { x, y });
+        }
+        self.hovered = cell;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key, pressed: bool, modifiers: Modifiers) -> Event {
+        Event::Key {
+            key,
+            pressed,
+            repeat: false,
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn modifiers_are_kept_per_key() {
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::NONE
+        };
+        let mut input = InputState::new();
+        input.handle(&key(Key::Shift, true, shift));
+        input.handle(&key(Key::A, true, shift));
+        input.handle(&key(Key::Shift, false, Modifiers::NONE));
+
+        assert!(input.is_pressed(Key::A));
+        assert_eq!(input.key_modifiers(Key::A), Some(shift));
+        assert_eq!(input.key_modifiers(Key::B), None);
+        assert_eq!(input.modifiers(), Modifiers::NONE);
+
+        // The press of a held key is remembered, released keys are forgotten
+        input.handle(&key(Key::B, true, Modifiers::NONE));
+        input.handle(&key(Key::B, false, Modifiers::NONE));
+        input.begin_frame();
+        assert_eq!(input.key_modifiers(Key::A), Some(shift));
+        assert_eq!(input.key_modifiers(Key::B), None);
+        assert_eq!(input.key_modifiers(Key::Shift), None);
+
+        // Some systems report a released modifier as still held
+        input.handle(&key(Key::Ctrl, true, Modifiers::NONE));
+        input.handle(&key(
+            Key::Ctrl,
+            false,
+            Modifiers {
+                ctrl: true,
+                ..Modifiers::NONE
+            },
+        ));
+        assert_eq!(input.modifiers(), Modifiers::NONE);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::console::{Cell, Console};
+
+/// A stack of equally sized consoles, such as map, entities, effects and HUD,
+/// that are composited from the bottom to the top layer
+#[derive(Debug, Clone)]
+pub struct Layers {
+    width: u32,
+    height: u32,
+    layers: Vec<Console>,
+}
+
+impl Layers {
+    /// Create a stack with an opaque bottom layer and transparent layers on top of it
+    pub fn new(width: u32, height: u32, count: usize) -> Layers {
+        let layers = (0..count)
+            .map(|i| {
+                if i == 0 {
+                    Console::new(width, height)
+                } else {
+                    Console::transparent(width, height)
+                }
+            })
+            .collect();
+        Layers {
+            width,
+            height,
+            layers,
+        }
+    }
+
+    /// Number of cells in the x direction
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Number of cells in the y direction
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Number of layers in the stack
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Whether the stack has no layers
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Add a transparent layer on top of the stack and return its index
+    pub fn push_layer(&mut self) -> usize {
+        self.layers
+            .push(Console::transparent(self.width, self.height));
+        self.layers.len() - 1
+    }
+
+    /// Retrieve a layer, index 0 is the bottom layer
+    pub fn layer(&self, index: usize) -> Option<&Console> {
+        self.layers.get(index)
+    }
+
+    /// Retrieve a layer for modification, index 0 is the bottom layer
+    pub fn layer_mut(&mut self, index: usize) -> Option<&mut Console> {
+        self.layers.get_mut(index)
+    }
+
+    /// Clear all the layers
+    pub fn clear(&mut self) {
+        for layer in self.layers.iter_mut() {
+            layer.clear();
+        }
+    }
+
+    /// Composite the layers into a single console
+    pub fn composite(&self) -> Console {
+        let mut result = Console::new(self.width, self.height);
+        self.composite_into(&mut result);
+        result
+    }
+
+    /// Composite the layers over the cells of an existing console,
+    /// cells outside of the console are skipped
+    pub fn composite_into(&self, target: &mut Console) {
+        for layer in &self.layers {
+            for (x, y, cell) in layer.cells() {
+                if let Some(below) = target.get_mut(x, y) {
+                    *below = cell.over(below);
+                }
+            }
+        }
+    }
+
+    /// The cell that is visible at a position after compositing
+    pub fn visible_cell(&self, x: u32, y: u32) -> Option<Cell> {
+        let mut result = Cell::default();
+        for layer in &self.layers {
+            result = layer.get(x, y)?.over(&result);
+        }
+        Some(result)
+    }
+}
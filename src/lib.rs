@@ -1,238 +1,471 @@
+#[cfg(feature = "opengl")]
 #[macro_use]
 extern crate glium;
 
-use glium::{glutin, Surface};
-use rusttype::gpu_cache::Cache;
-use rusttype::{point, Font, Scale, Vector};
-use std::borrow::Cow;
-use std::collections::HashMap;
-use std::error::Error;
+use crate::game::StateStack;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "opengl")]
+mod atlas;
+mod backend;
+mod bindings;
+#[cfg(feature = "opengl")]
+mod buffers;
+mod colour;
+mod console;
+mod error;
+mod frame;
+mod game;
+mod input;
+mod layers;
+#[cfg(feature = "opengl")]
 mod program;
+mod rect;
+mod text;
+mod tileset;
 mod util;
+#[cfg(feature = "opengl")]
 mod vertex;
 
+#[cfg(feature = "opengl")]
+pub use crate::atlas::{AtlasConfig, AtlasStats};
+#[cfg(feature = "opengl")]
+pub use crate::backend::{
+    create_window, get_dpi, get_physical_dimensions, GliumBackend, RogueFont,
+};
+pub use crate::backend::{Backend, HeadlessBackend, Image};
+#[cfg(feature = "terminal")]
+pub use crate::backend::{ColourMode, TerminalBackend};
+pub use crate::bindings::{Action, Bindings, Direction, KeyBinding, MovementKeys};
+pub use crate::colour::Colour;
+pub use crate::console::{Cell, Console};
+pub use crate::error::{Result, RoguelibError};
+pub use crate::frame::LineStyle;
+pub use crate::game::{Context, GameState, RunMode, Transition};
+pub use crate::input::{Event, InputState, Key, Modifiers, MouseButton};
+pub use crate::layers::Layers;
+pub use crate::rect::Rect;
+pub use crate::text::{measure, Align, StyledText};
+pub use crate::tileset::{Tileset, CP437};
+pub use crate::util::{FixedSize, GridOverlay, LineGrid, Scaling};
+
 /// Main structure for accessing the roguelib library
-pub struct Roguelib<'a> {
-    fonts: HashMap<String, RogueFont<'a>>,
-    grid_program: glium::Program,
-    text_program: glium::Program,
-    pub display: glium::Display,
-    pub event_loop: glutin::EventsLoop,
+pub struct Roguelib<B: Backend> {
+    backend: B,
+    input: InputState,
+    /// Game time between two updates of `run`
+    timestep: Duration,
+    run_mode: RunMode,
 }
 
-/// A font to use with associated glium structures
-pub struct RogueFont<'a> {
-    font: Font<'a>,
-    cache: Cache<'a>,
-    max_font_height: f32,
-    max_font_width: f32,
-    scale: Scale,
-    texture: glium::texture::Texture2d,
-}
+/// Most updates `run` catches up with in a single frame, time beyond that is dropped
+const MAX_UPDATES_PER_FRAME: u32 = 5;
 
-/// Create a window to use with the roguelib library
-pub fn create_window<S: Into<String>>(title: S) -> glutin::WindowBuilder {
-    glutin::WindowBuilder::new()
-        .with_dimensions((1920, 1080).into())
-        .with_title(title)
-}
+/// Shortest game time between two updates of `run`
+const MIN_TIMESTEP: Duration = Duration::from_millis(1);
 
-/// Retrieve physical dimension for the display
-pub fn get_physical_dimensions(display: &glium::Display) -> Result<(u32, u32), Box<dyn Error>> {
-    let dimensions: (u32, u32) = display
-        .gl_window()
-        .window()
-        .get_inner_size()
-        .ok_or("get_inner_size")?
-        .to_physical(get_dpi(display))
-        .into();
-    Ok(dimensions)
-}
+/// Longest time `run` sleeps between polling for input while it waits for the next update
+const MAX_SLEEP: Duration = Duration::from_millis(4);
 
-/// Get dpi factor
-pub fn get_dpi(display: &glium::Display) -> f64 {
-    display.gl_window().window().get_hidpi_factor()
+#[cfg(feature = "opengl")]
+impl Roguelib<GliumBackend> {
+    /// Initialize roguelib library stuff with a glium window
+    pub fn new(s: &str) -> Result<Roguelib<GliumBackend>> {
+        Ok(Roguelib::with_backend(GliumBackend::new(s)?))
+    }
 }
 
-impl<'a> Roguelib<'a> {
-    /// Initialize roguelib library stuff
-    pub fn new(s: &str) -> Roguelib<'a> {
-        let window = create_window(s);
-        let context = glutin::ContextBuilder::new().with_vsync(true);
-        let event_loop = glutin::EventsLoop::new();
-        let display = glium::Display::new(window, context, &event_loop).unwrap();
-
-        // Create the shaders for the grid
-        let grid_program = crate::program::create_grid_program(&display);
-        // Create the shaders for the text rendering
-        let text_program = crate::program::create_text_program(&display);
-
+impl<B: Backend> Roguelib<B> {
+    /// Initialize roguelib library stuff with a specific backend
+    pub fn with_backend(backend: B) -> Roguelib<B> {
         Roguelib {
-            fonts: HashMap::new(),
-            grid_program,
-            text_program,
-            display,
-            event_loop,
+            backend,
+            input: InputState::new(),
+            timestep: Duration::from_secs(1) / 60,
+            run_mode: RunMode::default(),
         }
     }
 
+    /// The backend used for rendering and input
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// The backend used for rendering and input
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
     /// Use a font for drawing purposes
-    pub fn add_font<S: Into<String>>(&mut self, name: S, font_bytes: &'static [u8], scale: f32) {
-        let dpi = get_dpi(&self.display);
-        let (width, height) =
-            get_physical_dimensions(&self.display).expect("Could not read window dimensions");
-        let font = Font::from_bytes(font_bytes).expect("Could not create font");
-        let scale = Scale::uniform(scale * dpi as f32);
-
-        // Calculate maximum height
-        let v_metrics = font.v_metrics(scale);
-        let max_font_height = v_metrics.ascent - v_metrics.descent; // + v_metrics.line_gap;
-
-        // Loop over box char to find the maximum width
-        use unicode_normalization::UnicodeNormalization;
-        let mut max_font_width = 0.0;
-        let box_char: String = "█".into();
-        for c in box_char.nfc() {
-            let glyph = font.glyph(c).scaled(scale);
-            let _bounding_box = glyph
-                .clone()
-                .positioned(point(0.0, 0.0))
-                .pixel_bounding_box()
-                .unwrap();
-            max_font_width = glyph.h_metrics().advance_width;
+    pub fn add_font<S: Into<String>>(
+        &mut self,
+        name: S,
+        font_bytes: &'static [u8],
+        scale: f32,
+    ) -> Result<()> {
+        crate::util::check_scale(scale)?;
+        let font = crate::util::parse_font(font_bytes)?;
+        self.backend.add_font(name.into(), font, scale)
+    }
+
+    /// Use a font that is loaded at runtime for drawing purposes
+    pub fn add_font_from_bytes<S: Into<String>>(
+        &mut self,
+        name: S,
+        font_bytes: Vec<u8>,
+        scale: f32,
+    ) -> Result<()> {
+        crate::util::check_scale(scale)?;
+        let font = crate::util::parse_font(font_bytes)?;
+        self.backend.add_font(name.into(), font, scale)
+    }
+
+    /// Use a TrueType or OpenType font file for drawing purposes
+    pub fn add_font_from_path<S: Into<String>, P: AsRef<Path>>(
+        &mut self,
+        name: S,
+        path: P,
+        scale: f32,
+    ) -> Result<()> {
+        crate::util::check_scale(scale)?;
+        let path = path.as_ref();
+        let font_bytes =
+            std::fs::read(path).map_err(|e| RoguelibError::Io(path.to_path_buf(), e))?;
+        self.add_font_from_bytes(name, font_bytes, scale)
+    }
+
+    /// Use a bitmap tileset as a font, the scale multiplies the size of the tiles
+    pub fn add_tileset<S: Into<String>>(
+        &mut self,
+        name: S,
+        tileset: Tileset,
+        scale: f32,
+    ) -> Result<()> {
+        crate::util::check_scale(scale)?;
+        self.backend.add_tileset(name.into(), tileset, scale)
+    }
+
+    /// Number of cells in the x and y direction that fit on the screen for the font
+    pub fn grid_size(&self, font: &str) -> Result<(u32, u32)> {
+        self.backend.grid_size(font)
+    }
+
+    /// Grid of the cells on the screen for the font, to convert between cells and pixels.
+    /// A fixed size console has the offset and scale it is drawn with
+    pub fn line_grid(&self, font: &str) -> Result<LineGrid> {
+        self.backend.line_grid(font)
+    }
+
+    /// Create a console that covers the whole screen for the font
+    pub fn create_console(&self, font: &str) -> Result<Console> {
+        let (width, height) = self.grid_size(font)?;
+        Ok(Console::new(width, height))
+    }
+
+    /// Draw the console in a grid, cells that do not fit on the screen are skipped
+    pub fn draw(&mut self, font: &str, console: &Console) -> Result<()> {
+        self.backend.present(font, console)
+    }
+
+    /// Composite the layers and draw the result in a grid
+    pub fn draw_layers(&mut self, font: &str, layers: &Layers) -> Result<()> {
+        self.draw(font, &layers.composite())
+    }
+
+    /// Create a stack of layers that covers the whole screen for the font
+    pub fn create_layers(&self, font: &str, count: usize) -> Result<Layers> {
+        let (width, height) = self.grid_size(font)?;
+        Ok(Layers::new(width, height, count))
+    }
+
+    /// Keep the number of columns and rows the same when the window is resized, the console
+    /// is scaled and centred on the screen, use `None` to fill the screen with cells
+    pub fn set_fixed_size(&mut self, size: Option<FixedSize>) {
+        self.backend.set_fixed_size(size)
+    }
+
+    /// Rasterize the glyphs for these characters before they are first drawn,
+    /// so the first frames do not have to
+    pub fn prewarm(&mut self, font: &str, chars: &str) -> Result<()> {
+        self.backend.prewarm(font, chars)
+    }
+
+    /// Draw lines between the cells of the grid, or stop drawing them with `None`,
+    /// debug builds draw white lines between all cells by default
+    pub fn set_grid_overlay(&mut self, overlay: Option<GridOverlay>) {
+        self.backend.set_grid_overlay(overlay)
+    }
+
+    /// Handle the input events that happened since the last call
+    pub fn poll_events<F: FnMut(Event)>(&mut self, mut callback: F) -> Result<()> {
+        self.backend.poll_events(&mut callback)
+    }
+
+    /// Wait until there is input and handle it, like `poll_events` but without
+    /// using any time while nothing happens
+    pub fn wait_events<F: FnMut(Event)>(&mut self, mut callback: F) -> Result<()> {
+        self.backend.wait_events(&mut callback)
+    }
+
+    /// Start a new frame of input with the events that happened since the last call,
+    /// events handled by `poll_events` are not part of it
+    pub fn poll_input(&mut self) -> Result<&InputState> {
+        let input = &mut self.input;
+        input.begin_frame();
+        self.backend
+            .poll_events(&mut |event| input.handle(&event))?;
+        Ok(&self.input)
+    }
+
+    /// Input of the current frame, as of the last call to `poll_input`
+    pub fn input(&self) -> &InputState {
+        &self.input
+    }
+
+    /// Game time between two updates of `run`, 60 updates a second by default. Timesteps
+    /// shorter than a millisecond are raised to a millisecond
+    pub fn set_timestep(&mut self, timestep: Duration) {
+        self.timestep = timestep.max(MIN_TIMESTEP);
+    }
+
+    /// Whether `run` updates the game continuously or waits for input, realtime by default
+    pub fn set_run_mode(&mut self, run_mode: RunMode) {
+        self.run_mode = run_mode;
+    }
+
+    /// Run the game until its last state stops or the window is closed. The state on top is
+    /// updated as the run mode decides and rendered with the font after the updates of a frame
+    pub fn run<S: GameState + 'static>(&mut self, font: &str, state: S) -> Result<()> {
+        let mut console = self.create_console(font)?;
+        let mut ctx = Context::new(self.timestep, console.width(), console.height());
+        let mut states = StateStack::new(Box::new(state), &mut ctx);
+        // The first frame is drawn before waiting for input
+        ctx.request_redraw();
+        let turn_based = self.run_mode == RunMode::TurnBased;
+        // The console as it was last drawn in turn based mode
+        let mut drawn: Option<Console> = None;
+
+        let mut previous = Instant::now();
+        let mut lag = Duration::from_secs(0);
+        while !states.is_empty() {
+            // Turn based games sleep until there is input, unless there is something to draw
+            let waiting = turn_based && !ctx.animating() && !ctx.redraw_requested();
+            // Input that no update has seen yet is kept
+            let input = &mut self.input;
+            let mut received = false;
+            let mut handle = |event: Event| {
+                received = true;
+                input.handle(&event);
+            };
+            if waiting {
+                self.backend.wait_events(&mut handle)?;
+            } else {
+                self.backend.poll_events(&mut handle)?;
+            }
+            if self.input.close_requested() {
+                states.stop(&mut ctx);
+                break;
+            }
+
+            let (width, height) = self.grid_size(font)?;
+            if (width, height) != (console.width(), console.height()) {
+                console = Console::new(width, height);
+                ctx.resize(width, height);
+                ctx.request_redraw();
+            }
+            if self.input.refresh_requested() {
+                ctx.request_redraw();
+            }
+
+            let now = Instant::now();
+            lag = (lag + (now - previous)).min(self.timestep * MAX_UPDATES_PER_FRAME);
+            previous = now;
+            let mut updated = false;
+            if turn_based && !ctx.animating() {
+                // Time spent waiting for input is not game time
+                lag = Duration::from_secs(0);
+                if received {
+                    states.update(&mut ctx, &self.input);
+                    self.input.begin_frame();
+                    updated = true;
+                }
+            } else {
+                while lag >= self.timestep && !states.is_empty() {
+                    states.update(&mut ctx, &self.input);
+                    self.input.begin_frame();
+                    lag -= self.timestep;
+                    updated = true;
+                }
+            }
+
+            if states.is_empty() {
+                break;
+            }
+            // Nothing changed, so wait for the next update instead of drawing the same frame,
+            // long timesteps are waited for in parts so input and resizes are still handled
+            if !updated && !ctx.redraw_requested() {
+                std::thread::sleep((self.timestep - lag).min(MAX_SLEEP));
+                continue;
+            }
+            console.clear();
+            states.render(&mut console);
+            let redraw = ctx.take_redraw();
+            if !turn_based || redraw || drawn.as_ref() != Some(&console) {
+                self.draw(font, &console)?;
+                if turn_based {
+                    drawn = Some(console.clone());
+                }
+            }
         }
+        Ok(())
+    }
+}
 
-        // Create the font texture
-        let cache_tex = glium::texture::Texture2d::with_format(
-            &self.display,
-            glium::texture::RawImage2d {
-                data: Cow::Owned(vec![128u8; width as usize * height as usize]),
-                width,
-                height,
-                format: glium::texture::ClientFormat::U8,
-            },
-            glium::texture::UncompressedFloatFormat::U8,
-            glium::texture::MipmapsOption::NoMipmap,
-        )
-        .expect("Could not create font texture");
-
-        // Create the font cache
-        let cache = Cache::builder().dimensions(width, height).build();
-
-        // Save the font
-        self.fonts.insert(
-            name.into(),
-            RogueFont {
-                font,
-                max_font_height,
-                max_font_width,
-                scale,
-                texture: cache_tex,
-                cache,
-            },
-        );
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_fonts_are_rejected() {
+        let font = include_bytes!("../fonts/consola.ttf");
+        let mut roguelib = Roguelib::with_backend(HeadlessBackend::new(100, 100));
+        for &length in &[0, 3, 12, 100, 1000, 20000, font.len() - 1] {
+            let truncated = font[..length].to_vec();
+            match roguelib.add_font_from_bytes("default", truncated, 20.0) {
+                Err(RoguelibError::FontParse(_)) => {}
+                result => panic!("font cut to {} bytes gave {:?}", length, result),
+            }
+        }
+        roguelib
+            .add_font_from_bytes("default", font.to_vec(), 20.0)
+            .unwrap();
     }
 
-    /// Draw the specific string in a grid
-    pub fn draw<S: Into<String>>(&mut self, font: &str, render_string: S) {
-        let (width, _): (u32, u32) =
-            get_physical_dimensions(&self.display).expect("Could not retrieve window dimensions");
+    #[test]
+    fn invalid_font_scales_are_rejected() {
+        let font = include_bytes!("../fonts/consola.ttf");
+        let mut roguelib = Roguelib::with_backend(HeadlessBackend::new(100, 100));
+        for &scale in &[0.0, -5.0, f32::NAN, f32::INFINITY] {
+            match roguelib.add_font("default", font, scale) {
+                Err(RoguelibError::InvalidScale(_)) => {}
+                result => panic!("scale {} gave {:?}", scale, result),
+            }
+            let parsed = crate::util::parse_font(&font[..]).unwrap();
+            match roguelib
+                .backend_mut()
+                .add_font("default".into(), parsed, scale)
+            {
+                Err(RoguelibError::InvalidScale(_)) => {}
+                result => panic!("scale {} gave {:?} in the backend", scale, result),
+            }
+        }
+        assert!(roguelib.line_grid("default").is_err());
+    }
 
-        let font = self
-            .fonts
-            .get_mut(font.into())
-            .expect("Font does not exist");
-        let font_vec = Vector {
-            x: font.max_font_width,
-            y: font.max_font_height,
+    #[test]
+    fn invalid_tileset_scales_are_rejected() {
+        let path = std::env::temp_dir().join(format!("roguelib-tiles-{}.png", std::process::id()));
+        Image::new(16, 16, Colour::WHITE).save_png(&path).unwrap();
+        let tileset = Tileset::from_path(&path, 2, 2).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut roguelib = Roguelib::with_backend(HeadlessBackend::new(100, 100));
+        for &scale in &[0.0, -5.0, f32::NAN, f32::INFINITY] {
+            match roguelib.add_tileset("tiles", tileset.clone(), scale) {
+                Err(RoguelibError::InvalidScale(_)) => {}
+                result => panic!("scale {} gave {:?}", scale, result),
+            }
+            match roguelib
+                .backend_mut()
+                .add_tileset("tiles".into(), tileset.clone(), scale)
+            {
+                Err(RoguelibError::InvalidScale(_)) => {}
+                result => panic!("scale {} gave {:?} in the backend", scale, result),
+            }
+        }
+        assert!(roguelib.line_grid("tiles").is_err());
+        roguelib.add_tileset("tiles", tileset, 2.0).unwrap();
+        assert_eq!(roguelib.grid_size("tiles").unwrap(), (6, 6));
+    }
+
+    #[test]
+    fn corrupted_font_directories_are_rejected() {
+        let font = include_bytes!("../fonts/consola.ttf");
+        // Position of the record of a table in the table directory
+        let record = |tag: &[u8]| {
+            let count = usize::from(u16::from_be_bytes([font[4], font[5]]));
+            (0..count)
+                .map(|table| 12 + table * 16)
+                .find(|&record| &font[record..record + 4] == tag)
+                .unwrap()
         };
+        let mut roguelib = Roguelib::with_backend(HeadlessBackend::new(100, 100));
+        let corruptions: Vec<(&str, usize, [u8; 4])> = vec![
+            ("head offset past the end", record(b"head") + 8, [0xff; 4]),
+            ("head length too short", record(b"head") + 12, [0, 0, 0, 8]),
+            ("hhea length too short", record(b"hhea") + 12, [0, 0, 0, 4]),
+            ("maxp length too short", record(b"maxp") + 12, [0, 0, 0, 2]),
+            ("cmap length too short", record(b"cmap") + 12, [0, 0, 0, 2]),
+            ("loca length too short", record(b"loca") + 12, [0, 0, 0, 4]),
+            ("glyf length too short", record(b"glyf") + 12, [0, 0, 0, 4]),
+            ("loca tag missing", record(b"loca"), *b"xxxx"),
+            ("table count too large", 4, [0xff, 0xff, 0, 0]),
+        ];
+        for (name, at, bytes) in corruptions {
+            let mut corrupted = font.to_vec();
+            corrupted[at..at + 4].copy_from_slice(&bytes);
+            match roguelib.add_font_from_bytes("default", corrupted, 20.0) {
+                Err(RoguelibError::FontParse(_)) => {}
+                result => panic!("font with {} gave {:?}", name, result),
+            }
+        }
+    }
 
-        let (grid, vb_grid, ib_grid) = crate::util::create_grid(
-            &font_vec,
-            &Vector {
-                x: 0.0,
-                y: font.font.v_metrics(font.scale).ascent,
-            },
-            &self.display,
-        );
+    /// State that fills the console with a background colour and never changes
+    struct Fill(Colour);
 
-        let glyphs =
-            crate::util::layout_grid(&font.font, font.scale, width, &grid, &render_string.into());
+    impl GameState for Fill {
+        fn update(&mut self, _ctx: &mut Context, _input: &InputState) -> Transition {
+            Transition::Continue
+        }
 
-        //         Queue the glyphs in the program
-        for glyph in &glyphs {
-            font.cache.queue_glyph(0, glyph.clone());
+        fn render(&mut self, console: &mut Console) {
+            console.fill(Cell {
+                bg: self.0,
+                ..Cell::default()
+            });
         }
-        //
-        // Cache the rects
-        let texture = &mut font.texture;
-        font.cache
-            .cache_queued(|rect, data| {
-                texture.main_level().write(
-                    glium::Rect {
-                        left: rect.min.x,
-                        bottom: rect.min.y,
-                        width: rect.width(),
-                        height: rect.height(),
-                    },
-                    glium::texture::RawImage2d {
-                        data: Cow::Borrowed(data),
-                        width: rect.width(),
-                        height: rect.height(),
-                        format: glium::texture::ClientFormat::U8,
-                    },
-                );
-            })
-            .expect("Could not queue texture data");
-
-        // Set the text uniforms
-        let text_uniforms = uniform! {
-            tex: texture.sampled().magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest)
-        };
+    }
 
-        // building the uniforms for the grid program
-        let uniforms_grid = uniform! {
-                matrix: [
-                    [1.0, 0.0, 0.0, 0.0],
-                    [0.0, 1.0, 0.0, 0.0],
-                    [0.0, 0.0, 1.0, 0.0],
-                    [0.0, 0.0, 0.0, 1.0f32]
-                ]
-        };
+    #[test]
+    fn turn_based_draws_before_input() {
+        let font = include_bytes!("../fonts/consola.ttf");
+        let backend = HeadlessBackend::new(100, 100).close_when_idle(true);
+        let mut roguelib = Roguelib::with_backend(backend);
+        roguelib.add_font("default", font, 20.0).unwrap();
+        roguelib.set_run_mode(RunMode::TurnBased);
+        roguelib.run("default", Fill(Colour::BLUE)).unwrap();
+        assert_eq!(
+            roguelib.backend().image().pixel(0, 0),
+            Some([0, 0, 255, 255])
+        );
+    }
 
-        // Create the vertex buffer and the grid
-        let text_vertex_buffer = crate::util::create_text_vb(&self.display, &glyphs, &font.cache);
-
-        let mut target = self.display.draw();
-        target.clear_color(0.0, 0.0, 0.0, 1.0);
-
-        // Draw the grid lines
-        target
-            .draw(
-                &vb_grid,
-                &ib_grid,
-                &self.grid_program,
-                &uniforms_grid,
-                &Default::default(),
-            )
-            .expect("Could not draw the grid lines");
-
-        // Draw the text
-        target
-            .draw(
-                &text_vertex_buffer,
-                glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList),
-                &self.text_program,
-                &text_uniforms,
-                &glium::DrawParameters {
-                    blend: glium::Blend::alpha_blending(),
-                    //                polygon_mode: glium::PolygonMode::Line,
-                    ..Default::default()
-                },
-            )
-            .expect("Could not draw text");
-
-        target.finish().expect("Could not execute finish command");
+    #[test]
+    fn turn_based_fails_without_input() {
+        let font = include_bytes!("../fonts/consola.ttf");
+        let mut roguelib = Roguelib::with_backend(HeadlessBackend::new(100, 100));
+        roguelib.add_font("default", font, 20.0).unwrap();
+        roguelib.set_run_mode(RunMode::TurnBased);
+        match roguelib.run("default", Fill(Colour::BLUE)) {
+            Err(RoguelibError::NoEvents) => {}
+            result => panic!("waiting without input gave {:?}", result),
+        }
+        // The first frame is still drawn
+        assert_eq!(
+            roguelib.backend().image().pixel(0, 0),
+            Some([0, 0, 255, 255])
+        );
     }
 }
@@ -1,14 +1,15 @@
+use crate::error::{Result, RoguelibError};
 use glium::program;
 
-pub fn create_grid_program(display: &glium::Display) -> glium::program::Program {
+pub fn create_grid_program(display: &glium::Display) -> Result<glium::program::Program> {
     let program = program!(display,
      140 => {
         vertex: "
                 #version 140
                 uniform mat4 matrix;
                 in vec2 position;
-                in vec3 color;
-                out vec3 vColor;
+                in vec4 color;
+                out vec4 vColor;
                 void main() {
                     gl_Position = vec4(position, 0.0, 1.0) * matrix;
                     vColor = color;
@@ -17,19 +18,19 @@ pub fn create_grid_program(display: &glium::Display) -> glium::program::Program
 
             fragment: "
                 #version 140
-                in vec3 vColor;
+                in vec4 vColor;
                 out vec4 f_color;
                 void main() {
-                    f_color = vec4(vColor, 1.0);
+                    f_color = vColor;
                 }
             "
             }
     );
 
-    program.unwrap()
+    program.map_err(RoguelibError::context)
 }
 
-pub fn create_text_program(display: &glium::Display) -> glium::program::Program {
+pub fn create_text_program(display: &glium::Display) -> Result<glium::program::Program> {
     let program = program!(
     display,
     140 => {
@@ -63,5 +64,110 @@ pub fn create_text_program(display: &glium::Display) -> glium::program::Program
             "
     });
 
-    program.unwrap()
+    program.map_err(RoguelibError::context)
+}
+
+pub fn create_background_program(display: &glium::Display) -> Result<glium::program::Program> {
+    let program = program!(
+    display,
+    140 => {
+        vertex: "
+                #version 140
+
+                in vec2 position;
+                in vec4 colour;
+
+                out vec4 v_colour;
+
+                void main() {
+                    gl_Position = vec4(position, 0.0, 1.0);
+                    v_colour = colour;
+                }
+            ",
+
+        fragment: "
+                #version 140
+                in vec4 v_colour;
+                out vec4 f_colour;
+
+                void main() {
+                    f_colour = v_colour;
+                }
+            "
+    });
+
+    program.map_err(RoguelibError::context)
+}
+
+pub fn create_instanced_text_program(display: &glium::Display) -> Result<glium::program::Program> {
+    let program = program!(
+    display,
+    140 => {
+        vertex: "
+                #version 140
+
+                in vec2 corner;
+                in vec4 glyph_rect;
+                in vec4 uv_rect;
+                in vec4 fg;
+
+                out vec2 v_tex_coords;
+                out vec4 v_colour;
+
+                void main() {
+                    gl_Position = vec4(mix(glyph_rect.xy, glyph_rect.zw, corner), 0.0, 1.0);
+                    v_tex_coords = mix(uv_rect.xy, uv_rect.zw, corner);
+                    v_colour = fg;
+                }
+            ",
+
+        fragment: "
+                #version 140
+                uniform sampler2D tex;
+                in vec2 v_tex_coords;
+                in vec4 v_colour;
+                out vec4 f_colour;
+
+                void main() {
+                    f_colour = v_colour * vec4(1.0, 1.0, 1.0, texture(tex, v_tex_coords).r);
+                }
+            "
+    });
+
+    program.map_err(RoguelibError::context)
+}
+
+pub fn create_instanced_background_program(
+    display: &glium::Display,
+) -> Result<glium::program::Program> {
+    let program = program!(
+    display,
+    140 => {
+        vertex: "
+                #version 140
+
+                in vec2 corner;
+                in vec4 cell_rect;
+                in vec4 bg;
+
+                out vec4 v_colour;
+
+                void main() {
+                    gl_Position = vec4(mix(cell_rect.xy, cell_rect.zw, corner), 0.0, 1.0);
+                    v_colour = bg;
+                }
+            ",
+
+        fragment: "
+                #version 140
+                in vec4 v_colour;
+                out vec4 f_colour;
+
+                void main() {
+                    f_colour = v_colour;
+                }
+            "
+    });
+
+    program.map_err(RoguelibError::context)
 }
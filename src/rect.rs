@@ -0,0 +1,87 @@
+/// A rectangular region of cells
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// Create a rectangle from its top left corner and size
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the cell lies inside the rectangle
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.width && y - self.y < self.height
+    }
+
+    /// The part of the rectangle that lies inside another rectangle, empty when they do not
+    /// overlap
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        // Computed in 64 bits, rectangles may reach past the largest position
+        let right = (u64::from(self.x) + u64::from(self.width))
+            .min(u64::from(other.x) + u64::from(other.width));
+        let bottom = (u64::from(self.y) + u64::from(self.height))
+            .min(u64::from(other.y) + u64::from(other.height));
+        Rect {
+            x,
+            y,
+            width: right.saturating_sub(u64::from(x)) as u32,
+            height: bottom.saturating_sub(u64::from(y)) as u32,
+        }
+    }
+
+    /// Iterate over the positions of all the cells in the rectangle, row by row. Cells past the
+    /// largest position are skipped
+    pub fn cells(&self) -> impl Iterator<Item = (u32, u32)> {
+        let rect = *self;
+        (rect.y..rect.y.saturating_add(rect.height))
+            .flat_map(move |y| (rect.x..rect.x.saturating_add(rect.width)).map(move |x| (x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersection_of_overlapping_rects() {
+        let a = Rect::new(1, 1, 4, 4);
+        assert_eq!(
+            a.intersection(&Rect::new(3, 0, 10, 2)),
+            Rect::new(3, 1, 2, 1)
+        );
+        assert_eq!(a.intersection(&a), a);
+        // Rects that only touch do not overlap
+        assert_eq!(a.intersection(&Rect::new(5, 1, 2, 2)).cells().count(), 0);
+        assert_eq!(a.intersection(&Rect::new(10, 10, 2, 2)).cells().count(), 0);
+    }
+
+    #[test]
+    fn intersection_past_the_largest_position() {
+        let everything = Rect::new(0, 0, u32::MAX, u32::MAX);
+        let edge = Rect::new(u32::MAX - 1, u32::MAX - 1, u32::MAX, u32::MAX);
+        assert_eq!(
+            edge.intersection(&everything),
+            Rect::new(u32::MAX - 1, u32::MAX - 1, 1, 1)
+        );
+        assert_eq!(
+            Rect::new(2, 3, u32::MAX, u32::MAX).intersection(&Rect::new(0, 0, 4, 5)),
+            Rect::new(2, 3, 2, 2)
+        );
+        assert_eq!(
+            edge.cells().collect::<Vec<_>>(),
+            vec![(u32::MAX - 1, u32::MAX - 1)]
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use crate::colour::Colour;
+use crate::error::{Result, RoguelibError};
+use std::ops::Range;
+
+/// Horizontal alignment of the lines of text in a rectangle
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Centre,
+    Right,
+}
+
+impl Align {
+    /// Offset of a line of the given length in a row of the given width
+    pub(crate) fn offset(self, length: u32, width: u32) -> u32 {
+        let space = width.saturating_sub(length);
+        match self {
+            Align::Left => 0,
+            Align::Centre => space / 2,
+            Align::Right => space,
+        }
+    }
+}
+
+/// A character of styled text, colours that are not set keep the colour of the cell
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct StyledChar {
+    pub glyph: char,
+    pub fg: Option<Colour>,
+    pub bg: Option<Colour>,
+}
+
+impl From<char> for StyledChar {
+    fn from(glyph: char) -> StyledChar {
+        StyledChar {
+            glyph,
+            fg: None,
+            bg: None,
+        }
+    }
+}
+
+/// Text with colours for parts of it, parsed from markup like `You hit the [fg=red]orc[/]`
+///
+/// `[fg=colour]` and `[bg=colour]` set the foreground or background colour until the matching
+/// `[/]`, colours are names like `dark_grey` or hex values like `#202020`. Tags that are not
+/// closed apply to the rest of the text, `[[` and `]]` are literal brackets
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledText {
+    chars: Vec<StyledChar>,
+}
+
+/// The colour a tag sets
+#[derive(Debug, Copy, Clone)]
+enum Layer {
+    Fg,
+    Bg,
+}
+
+impl StyledText {
+    /// Parse markup into styled text, malformed tags and unescaped brackets are errors
+    pub fn parse(markup: &str) -> Result<StyledText> {
+        let mut chars = Vec::new();
+        let (mut fg, mut bg) = (None, None);
+        // Open tags with the colour they replaced
+        let mut open: Vec<(Layer, Option<Colour>)> = Vec::new();
+
+        let mut input = markup.chars().enumerate().peekable();
+        while let Some((position, c)) = input.next() {
+            match c {
+                '[' if input.peek().map(|(_, c)| *c) == Some('[') => {
+                    input.next();
+                    chars.push(StyledChar { glyph: '[', fg, bg });
+                }
+                ']' if input.peek().map(|(_, c)| *c) == Some(']') => {
+                    input.next();
+                    chars.push(StyledChar { glyph: ']', fg, bg });
+                }
+                ']' => {
+                    return Err(RoguelibError::Markup(format!(
+                        "unescaped ']' at {}, use ']]' for a literal bracket",
+                        position
+                    )))
+                }
+                '[' => {
+                    let mut tag = String::new();
+                    loop {
+                        match input.next() {
+                            Some((_, ']')) => break,
+                            Some((_, '[')) | None => {
+                                return Err(RoguelibError::Markup(format!(
+                                    "tag at {} is not closed with ']'",
+                                    position
+                                )))
+                            }
+                            Some((_, c)) => tag.push(c),
+                        }
+                    }
+
+                    if tag == "/" {
+                        match open.pop() {
+                            Some((Layer::Fg, previous)) => fg = previous,
+                            Some((Layer::Bg, previous)) => bg = previous,
+                            None => {
+                                return Err(RoguelibError::Markup(format!(
+                                    "'[/]' at {} does not close a tag",
+                                    position
+                                )))
+                            }
+                        }
+                        continue;
+                    }
+
+                    let (layer, value) = match tag.find('=') {
+                        Some(i) if &tag[..i] == "fg" => (Layer::Fg, &tag[i + 1..]),
+                        Some(i) if &tag[..i] == "bg" => (Layer::Bg, &tag[i + 1..]),
+                        _ => {
+                            return Err(RoguelibError::Markup(format!(
+                                "unknown tag '[{}]' at {}",
+                                tag, position
+                            )))
+                        }
+                    };
+                    let colour = Colour::from_name(value)
+                        .or_else(|| Colour::from_hex(value))
+                        .ok_or_else(|| {
+                            RoguelibError::Markup(format!(
+                                "unknown colour '{}' at {}",
+                                value, position
+                            ))
+                        })?;
+                    match layer {
+                        Layer::Fg => open.push((layer, fg.replace(colour))),
+                        Layer::Bg => open.push((layer, bg.replace(colour))),
+                    }
+                }
+                glyph => chars.push(StyledChar { glyph, fg, bg }),
+            }
+        }
+        Ok(StyledText { chars })
+    }
+
+    /// The text without markup
+    pub fn plain(&self) -> String {
+        self.chars.iter().map(|c| c.glyph).collect()
+    }
+
+    /// Number of lines the text needs when it is wrapped at the width
+    pub fn measure(&self, width: u32) -> u32 {
+        let glyphs: Vec<char> = self.chars.iter().map(|c| c.glyph).collect();
+        wrap(&glyphs, width).len() as u32
+    }
+
+    /// The characters with their colours
+    pub(crate) fn chars(&self) -> &[StyledChar] {
+        &self.chars
+    }
+}
+
+/// Number of lines the text needs when it is wrapped at the width
+pub fn measure(text: &str, width: u32) -> u32 {
+    let chars: Vec<char> = text.chars().collect();
+    wrap(&chars, width).len() as u32
+}
+
+/// Ranges of the lines of the text when it is wrapped at the width, lines break at newlines
+/// and between words, words that are longer than a line are split over multiple lines
+pub(crate) fn wrap(chars: &[char], width: u32) -> Vec<Range<usize>> {
+    let width = width as usize;
+    let mut lines = Vec::new();
+    if width == 0 {
+        return lines;
+    }
+
+    let mut start = 0;
+    for paragraph in chars.split(|c| *c == '\n') {
+        let mut line: Option<Range<usize>> = None;
+        for mut word in words(paragraph, start) {
+            // Split words that do not fit on a line of their own
+            while word.len() > width {
+                if let Some(line) = line.take() {
+                    lines.push(line);
+                }
+                lines.push(word.start..word.start + width);
+                word.start += width;
+            }
+            if word.is_empty() {
+                continue;
+            }
+
+            line = match line {
+                Some(current) if word.end - current.start <= width => Some(current.start..word.end),
+                Some(current) => {
+                    lines.push(current);
+                    Some(word)
+                }
+                None => Some(word),
+            };
+        }
+        // Empty paragraphs are empty lines
+        lines.push(line.unwrap_or(start..start));
+        start += paragraph.len() + 1;
+    }
+    lines
+}
+
+/// Ranges of the words of a paragraph that starts at an offset
+fn words(paragraph: &[char], offset: usize) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut word_start = None;
+    for (i, c) in paragraph.iter().enumerate() {
+        match (c.is_whitespace(), word_start) {
+            (true, Some(start)) => {
+                words.push(offset + start..offset + i);
+                word_start = None;
+            }
+            (false, None) => word_start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(start) = word_start {
+        words.push(offset + start..offset + paragraph.len());
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Foreground and background colours of every character
+    fn colours(text: &StyledText) -> Vec<(Option<Colour>, Option<Colour>)> {
+        text.chars().iter().map(|c| (c.fg, c.bg)).collect()
+    }
+
+    /// Message of a markup error
+    fn error(markup: &str) -> String {
+        match StyledText::parse(markup) {
+            Err(RoguelibError::Markup(e)) => e,
+            result => panic!("'{}' gave {:?}", markup, result),
+        }
+    }
+
+    /// Lines of text wrapped at a width
+    fn lines(text: &str, width: u32) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        wrap(&chars, width)
+            .into_iter()
+            .map(|line| chars[line].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn nested_tags() {
+        let text = StyledText::parse("a[fg=red]b[bg=#0000ff]c[fg=green]d[/]e[/]f[/]g").unwrap();
+        let (red, blue, green) = (Some(Colour::RED), Some(Colour::BLUE), Some(Colour::GREEN));
+        assert_eq!(text.plain(), "abcdefg");
+        assert_eq!(
+            colours(&text),
+            vec![
+                (None, None),
+                (red, None),
+                (red, blue),
+                (green, blue),
+                (red, blue),
+                (red, None),
+                (None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_brackets() {
+        let text = StyledText::parse("[[x]] [fg=red][[[/]").unwrap();
+        assert_eq!(text.plain(), "[x] [");
+        assert_eq!(text.chars()[4].fg, Some(Colour::RED));
+    }
+
+    #[test]
+    fn markup_errors() {
+        assert_eq!(error("ab[fg=red"), "tag at 2 is not closed with ']'");
+        assert_eq!(error("[fg=red[/]"), "tag at 0 is not closed with ']'");
+        assert_eq!(error("a[/]"), "'[/]' at 1 does not close a tag");
+        assert_eq!(error("[fg=red]a[/][/]"), "'[/]' at 12 does not close a tag");
+        assert_eq!(error("[fg=mauve]a"), "unknown colour 'mauve' at 0");
+        assert_eq!(error("[bg=#12345]a"), "unknown colour '#12345' at 0");
+        assert_eq!(error("[fg=##ffffff]a"), "unknown colour '##ffffff' at 0");
+        assert_eq!(error("[fg=#+f+f+f]a"), "unknown colour '#+f+f+f' at 0");
+        assert_eq!(error("[bold]a"), "unknown tag '[bold]' at 0");
+        assert_eq!(
+            error("a]b"),
+            "unescaped ']' at 1, use ']]' for a literal bracket"
+        );
+    }
+
+    #[test]
+    fn wrap_empty_text() {
+        assert_eq!(lines("", 10), vec![""]);
+        assert_eq!(measure("", 10), 1);
+        assert_eq!(measure("text", 0), 0);
+    }
+
+    #[test]
+    fn wrap_long_words() {
+        assert_eq!(lines("abcdefgh", 3), vec!["abc", "def", "gh"]);
+        assert_eq!(lines("a abcdefgh b", 4), vec!["a", "abcd", "efgh", "b"]);
+        assert_eq!(lines("one two three", 7), vec!["one two", "three"]);
+    }
+
+    #[test]
+    fn wrap_trailing_newline() {
+        assert_eq!(lines("a b\n", 10), vec!["a b", ""]);
+        assert_eq!(lines("a\n\nb", 10), vec!["a", "", "b"]);
+        assert_eq!(measure("a b\n", 10), 2);
+    }
+}
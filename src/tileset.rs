@@ -0,0 +1,128 @@
+use crate::error::{Result, RoguelibError};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Unicode characters for the 256 tiles of a code page 437 sheet, in sheet order
+pub const CP437: [char; 256] = [
+    '\0', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼', //
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼', //
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/', //
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?', //
+    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', //
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '\\', ']', '^', '_', //
+    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', //
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~', '⌂', //
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}', //
+];
+
+/// A bitmap font made of equally sized tiles in a grid-aligned image sheet
+#[derive(Debug, Clone)]
+pub struct Tileset {
+    /// Coverage of every pixel of the sheet, used as the alpha of the foreground colour
+    coverage: Vec<u8>,
+    width: u32,
+    height: u32,
+    columns: u32,
+    rows: u32,
+    mapping: HashMap<char, u32>,
+}
+
+impl Tileset {
+    /// Load a sheet with the given number of tile columns and rows from png or bmp data,
+    /// characters are mapped with the code page 437 table
+    pub fn from_bytes(bytes: &[u8], columns: u32, rows: u32) -> Result<Tileset> {
+        let image = image::load_from_memory(bytes)
+            .map_err(|e| RoguelibError::TilesetParse(e.to_string()))?
+            .to_rgba();
+        let (width, height) = image.dimensions();
+        if columns == 0 || rows == 0 || width % columns != 0 || height % rows != 0 {
+            return Err(RoguelibError::TilesetParse(format!(
+                "{}x{} image can not be split into {}x{} tiles",
+                width, height, columns, rows
+            )));
+        }
+
+        // Opaque sheets use their brightness, magenta is the usual key colour for the background
+        let coverage = image
+            .pixels()
+            .map(|p| match p.data {
+                [255, 0, 255, _] => 0,
+                [r, g, b, a] => (u32::from(r.max(g).max(b)) * u32::from(a) / 255) as u8,
+            })
+            .collect();
+
+        Ok(Tileset {
+            coverage,
+            width,
+            height,
+            columns,
+            rows,
+            mapping: CP437
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (*c, i as u32))
+                .collect(),
+        })
+    }
+
+    /// Load a sheet with the given number of tile columns and rows from a png or bmp file
+    pub fn from_path<P: AsRef<Path>>(path: P, columns: u32, rows: u32) -> Result<Tileset> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|e| RoguelibError::Io(path.to_path_buf(), e))?;
+        Tileset::from_bytes(&bytes, columns, rows)
+    }
+
+    /// Replace the code page 437 table with a custom mapping from characters to tile indices
+    pub fn with_mapping(mut self, mapping: HashMap<char, u32>) -> Tileset {
+        self.mapping = mapping;
+        self
+    }
+
+    /// Map a single character to a tile index, counted row by row from the top left
+    pub fn map(&mut self, c: char, tile: u32) {
+        self.mapping.insert(c, tile);
+    }
+
+    /// Index of the tile for a character
+    pub fn tile_for(&self, c: char) -> Option<u32> {
+        self.mapping
+            .get(&c)
+            .cloned()
+            .filter(|tile| *tile < self.columns * self.rows)
+    }
+
+    /// Width of a single tile in pixels
+    pub fn tile_width(&self) -> u32 {
+        self.width / self.columns
+    }
+
+    /// Height of a single tile in pixels
+    pub fn tile_height(&self) -> u32 {
+        self.height / self.rows
+    }
+
+    /// Width and height of the whole sheet in pixels
+    pub(crate) fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Coverage of the sheet, row by row from the top left
+    pub(crate) fn coverage(&self) -> &[u8] {
+        &self.coverage
+    }
+
+    /// Pixel position of the top left corner of a tile in the sheet
+    pub(crate) fn tile_origin(&self, tile: u32) -> (u32, u32) {
+        (
+            (tile % self.columns) * self.tile_width(),
+            (tile / self.columns) * self.tile_height(),
+        )
+    }
+}
@@ -1,117 +1,379 @@
-use crate::vertex::{TextVertex, Vertex};
-use rusttype::gpu_cache::Cache;
-use rusttype::{point, vector, Font, PositionedGlyph, Rect, Scale, Vector};
-use unicode_normalization::UnicodeNormalization;
-
-/// Create the text vertex buffer, this is the buffer that contains text rectangles
-pub(crate) fn create_text_vb(
-    display: &glium::Display,
-    glyphs: &Vec<PositionedGlyph>,
-    cache: &Cache,
-) -> glium::VertexBuffer<TextVertex> {
-    let vertex_buffer = {
-        let colour = [1.0, 1.0, 1.0, 1.0];
-        let (screen_width, screen_height) = {
-            let (w, h) = display.get_framebuffer_dimensions();
-            (w as f32, h as f32)
-        };
-        let origin = point(0.0, 0.0);
-        let vertices: Vec<TextVertex> = glyphs
-            .iter()
-            .flat_map(|g| {
-                if let Ok(Some((uv_rect, screen_rect))) = cache.rect_for(0, g) {
-                    // Scale between 0..1 to -1..1
-                    let gl_rect = Rect {
-                        min: origin
-                            + (vector(
-                                screen_rect.min.x as f32 / screen_width - 0.5,
-                                1.0 - screen_rect.min.y as f32 / screen_height - 0.5,
-                            )) * 2.0,
-                        max: origin
-                            + (vector(
-                                screen_rect.max.x as f32 / screen_width - 0.5,
-                                1.0 - screen_rect.max.y as f32 / screen_height - 0.5,
-                            )) * 2.0,
-                    };
-
-                    // Associate vertices with texture coords
-                    arrayvec::ArrayVec::<[TextVertex; 6]>::from([
-                        TextVertex {
-                            position: [gl_rect.min.x, gl_rect.max.y],
-                            tex_coords: [uv_rect.min.x, uv_rect.max.y],
-                            colour,
-                        },
-                        TextVertex {
-                            position: [gl_rect.min.x, gl_rect.min.y],
-                            tex_coords: [uv_rect.min.x, uv_rect.min.y],
-                            colour,
-                        },
-                        TextVertex {
-                            position: [gl_rect.max.x, gl_rect.min.y],
-                            tex_coords: [uv_rect.max.x, uv_rect.min.y],
-                            colour,
-                        },
-                        TextVertex {
-                            position: [gl_rect.max.x, gl_rect.min.y],
-                            tex_coords: [uv_rect.max.x, uv_rect.min.y],
-                            colour,
-                        },
-                        TextVertex {
-                            position: [gl_rect.max.x, gl_rect.max.y],
-                            tex_coords: [uv_rect.max.x, uv_rect.max.y],
-                            colour,
-                        },
-                        TextVertex {
-                            position: [gl_rect.min.x, gl_rect.max.y],
-                            tex_coords: [uv_rect.min.x, uv_rect.max.y],
-                            colour,
-                        },
-                    ])
-                } else {
-                    arrayvec::ArrayVec::new()
-                }
-            })
-            .collect();
-
-        glium::VertexBuffer::new(display, &vertices).expect("Could not create text vertex buffer")
-    };
+use crate::colour::Colour;
+use crate::console::{Cell, Console};
+use crate::error::{Result, RoguelibError};
+use rusttype::{point, Font, Point, PositionedGlyph, Rect, Scale, Segment, SharedBytes, Vector};
 
-    vertex_buffer
+/// Layout the cells of a console for a specific font in a grid
+pub(crate) fn layout_grid<'a>(
+    font: &Font<'a>,
+    scale: Scale,
+    grid: &LineGrid,
+    console: &Console,
+) -> Vec<(PositionedGlyph<'a>, Colour)> {
+    console
+        .cells()
+        // Cells that do not fit on the screen are not drawn
+        .filter(|(x, y, _)| grid.contains(*x, *y))
+        .map(|(x, y, cell)| (layout_cell(font, scale, grid, x, y, cell), cell.fg))
+        .collect()
 }
 
-/// Layout for a specific font in a grid
-pub(crate) fn layout_grid<'a>(
+/// Position the glyph of a single cell, scaled along with the grid. The origin is snapped to a
+/// whole pixel so every cell rasterizes a glyph the same way
+pub(crate) fn layout_cell<'a>(
     font: &Font<'a>,
     scale: Scale,
-    _width: u32,
     grid: &LineGrid,
-    text: &str,
-) -> Vec<PositionedGlyph<'a>> {
-    let mut result = Vec::new();
-    let mut index = grid.totals.x;
-    for c in text.nfc() {
-        let base_glyph = font.glyph(c);
-        let grid_pos = grid.coordinates_for(index);
-        let glyph = base_glyph
-            .scaled(scale)
-            .positioned(point(grid_pos.x, grid_pos.y));
-        result.push(glyph);
-        index += 1;
+    x: u32,
+    y: u32,
+    cell: &Cell,
+) -> PositionedGlyph<'a> {
+    let scale = Scale {
+        x: scale.x * grid.scale,
+        y: scale.y * grid.scale,
+    };
+    let grid_pos = grid.cell_coordinates(x, y);
+    font.glyph(cell.glyph)
+        .scaled(scale)
+        .positioned(point(grid_pos.x.round(), grid_pos.y.round()))
+}
+
+/// Calculate the dimensions of a single cell for a font
+pub(crate) fn cell_dimensions(font: &Font, scale: Scale) -> Result<Vector<f32>> {
+    // Calculate maximum height
+    let v_metrics = font.v_metrics(scale);
+    let max_font_height = v_metrics.ascent - v_metrics.descent; // + v_metrics.line_gap;
+
+    // Loop over box char to find the maximum width
+    use unicode_normalization::UnicodeNormalization;
+    let mut max_font_width = 0.0;
+    let box_char: String = "█".into();
+    for c in box_char.nfc() {
+        let glyph = font.glyph(c).scaled(scale);
+        let _bounding_box = glyph
+            .clone()
+            .positioned(point(0.0, 0.0))
+            .pixel_bounding_box()
+            .ok_or(RoguelibError::MissingGlyph(c))?;
+        max_font_width = glyph.h_metrics().advance_width;
+    }
+
+    Ok(Vector {
+        x: max_font_width,
+        y: max_font_height,
+    })
+}
+
+/// Check that a font or tileset scale is a finite number above zero, other scales give cells
+/// without a size
+pub(crate) fn check_scale(scale: f32) -> Result<f32> {
+    if scale.is_finite() && scale > 0.0 {
+        Ok(scale)
+    } else {
+        Err(RoguelibError::InvalidScale(scale))
+    }
+}
+
+/// Parse a TrueType font, font data the parser would panic on is an error instead
+pub(crate) fn parse_font<B: Into<SharedBytes<'static>>>(bytes: B) -> Result<Font<'static>> {
+    let bytes = bytes.into();
+    check_font_tables(&bytes)?;
+
+    // The checks do not cover every structure the parser reads, like the contents of the
+    // glyph outlines, so a sample of the glyphs is probed and a panic is a broken font as well.
+    // The panic hook of the program still reports the panic, and builds with
+    // `panic = "abort"` can not catch it at all
+    std::panic::catch_unwind(move || {
+        let font = Font::from_bytes(bytes)?;
+        if probe_font(&font) {
+            Ok(font)
+        } else {
+            Err(ill_formed())
+        }
+    })
+    .unwrap_or(Err(ill_formed()))
+}
+
+/// Characters whose glyphs are probed, the printable ASCII characters and the block the cells
+/// are measured with
+const PROBE_CHARS: &str = " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`\
+                           abcdefghijklmnopqrstuvwxyz{|}~\u{2588}";
+
+/// Whether the probed glyphs and metrics of a font fit the size of the font, broken sizes make
+/// the rasterizer loop for a very long time or fill the screen with millions of cells and
+/// outlines outside of the bounding box of their glyph make it panic. Glyphs outside of the
+/// sample are not checked, so drawing them may still panic
+fn probe_font(font: &Font) -> bool {
+    let scale = Scale::uniform(16.0);
+    let sane = |d: f32| d >= scale.y / 10.0 && d <= scale.y * 10.0;
+    let fits = |d: f32| (0.0..=scale.y * 10.0).contains(&d);
+    for c in PROBE_CHARS.chars() {
+        let glyph = font.glyph(c).scaled(scale);
+        let b = match glyph.exact_bounding_box() {
+            Some(b) => b,
+            None => continue,
+        };
+        if !fits(b.width()) || !fits(b.height()) {
+            return false;
+        }
+        // Outlines point upwards while the bounding box points downwards
+        let inside = |p: &Point<f32>| {
+            p.x >= b.min.x - 1.0
+                && p.x <= b.max.x + 1.0
+                && -p.y >= b.min.y - 1.0
+                && -p.y <= b.max.y + 1.0
+        };
+        let inside_segment = |segment: &Segment| match segment {
+            Segment::Line(line) => line.p.iter().all(inside),
+            Segment::Curve(curve) => curve.p.iter().all(inside),
+        };
+        let shape = glyph.shape().unwrap_or_default();
+        let mut segments = shape.iter().flat_map(|contour| contour.segments.iter());
+        if !segments.all(inside_segment) {
+            return false;
+        }
+        glyph.positioned(point(0.0, 0.0)).draw(|_, _, _| {});
+    }
+
+    // Fonts without a block glyph are reported when they are added
+    match cell_dimensions(font, scale) {
+        Ok(cell) => sane(cell.x) && sane(cell.y),
+        Err(_) => true,
+    }
+}
+
+/// Deepest nesting of composite glyphs that is accepted, real fonts nest a few levels
+const MAX_COMPOSITE_DEPTH: usize = 16;
+
+/// Error for font data the parser would panic on
+fn ill_formed() -> RoguelibError {
+    RoguelibError::FontParse(rusttype::Error::IllFormed)
+}
+
+/// Big endian integer of 2 or 4 bytes of the font data
+fn read_font(bytes: &[u8], at: usize, size: usize) -> Result<usize> {
+    let end = at.checked_add(size).ok_or_else(ill_formed)?;
+    let b = bytes.get(at..end).ok_or_else(ill_formed)?;
+    Ok(b.iter().fold(0, |n, b| n << 8 | usize::from(*b)))
+}
+
+/// Check that the tables the font parser reads are in the data and large enough for what it
+/// reads from them, the parser panics on data that is cut off instead of returning an error
+fn check_font_tables(bytes: &[u8]) -> Result<()> {
+    let read = |at: usize, size: usize| read_font(bytes, at, size);
+
+    // Collections start with the offsets of their fonts, only the first font is used
+    let font = if bytes.starts_with(b"ttcf") {
+        read(12, 4)?
+    } else {
+        0
+    };
+    let count = read(font + 4, 2)?;
+    let mut tables = Vec::with_capacity(count);
+    for table in 0..count {
+        let record = font + 12 + table * 16;
+        let tag = bytes.get(record..record + 4).ok_or_else(ill_formed)?;
+        let (offset, length) = (read(record + 8, 4)?, read(record + 12, 4)?);
+        match offset.checked_add(length) {
+            // Offsets are added as 32 bit integers by the parser
+            Some(end) if end <= bytes.len() && end <= u32::MAX as usize => {}
+            _ => return Err(ill_formed()),
+        }
+        tables.push((tag, offset, length));
+    }
+    // Offset of a required table that has at least the length
+    let table = |tag: &[u8], min_length: usize| -> Result<(usize, usize)> {
+        match tables.iter().find(|(t, _, _)| *t == tag) {
+            Some(&(_, offset, length)) if length >= min_length => Ok((offset, length)),
+            _ => Err(ill_formed()),
+        }
+    };
+
+    let (head, _) = table(b"head", 54)?;
+    let (hhea, _) = table(b"hhea", 36)?;
+    let (maxp, _) = table(b"maxp", 6)?;
+    let glyphs = read(maxp + 4, 2)?;
+    let metrics = read(hhea + 34, 2)?;
+    // Glyphs after the last full metric only have a left side bearing
+    table(b"hmtx", 4 * metrics + 2 * glyphs.saturating_sub(metrics))?;
+
+    let (cmap, cmap_length) = table(b"cmap", 4)?;
+    let encodings = read(cmap + 2, 2)?;
+    if cmap_length < 4 + 8 * encodings {
+        return Err(ill_formed());
+    }
+    for encoding in 0..encodings {
+        // Format and length of the encoding subtable
+        let subtable = read(cmap + 4 + 8 * encoding + 4, 4)?;
+        if subtable + 4 > cmap_length {
+            return Err(ill_formed());
+        }
+    }
+
+    // Glyph offsets are stored as 16 bit halves or as 32 bit integers
+    let (entry, factor) = match read(head + 50, 2)? {
+        0 => (2, 2),
+        _ => (4, 1),
+    };
+    let (loca, _) = table(b"loca", entry * (glyphs + 1))?;
+    let (glyf, glyf_length) = table(b"glyf", 0)?;
+    let mut offsets = Vec::with_capacity(glyphs + 1);
+    for glyph in 0..=glyphs {
+        let offset = read(loca + glyph * entry, entry)? * factor;
+        if offset > glyf_length {
+            return Err(ill_formed());
+        }
+        offsets.push(glyf + offset);
+    }
+
+    let mut glyf = GlyphTable {
+        bytes,
+        offsets,
+        depths: vec![None; glyphs],
+    };
+    for glyph in 0..glyphs {
+        glyf.depth(glyph, 0)?;
+    }
+    Ok(())
+}
+
+/// Glyphs of a font, to check the composite glyphs the parser follows recursively
+struct GlyphTable<'a> {
+    bytes: &'a [u8],
+    /// Start of every glyph in the data, followed by the end of the last glyph
+    offsets: Vec<usize>,
+    /// Nesting of the glyphs that have been checked
+    depths: Vec<Option<usize>>,
+}
+
+impl<'a> GlyphTable<'a> {
+    /// Nesting of a glyph, composite glyphs that refer to missing glyphs, to themselves or
+    /// that nest too deep are errors
+    fn depth(&mut self, glyph: usize, level: usize) -> Result<usize> {
+        if level > MAX_COMPOSITE_DEPTH {
+            return Err(ill_formed());
+        }
+        let start = *self.offsets.get(glyph).ok_or_else(ill_formed)?;
+        if let Some(depth) = self.depths[glyph] {
+            return Ok(depth);
+        }
+        // Empty glyphs and glyphs with contours do not refer to other glyphs
+        let bytes = self.bytes;
+        let read = |at: usize, size: usize| read_font(bytes, at, size);
+        let empty = start == self.offsets[glyph + 1];
+        if empty || read(start, 2)? < 0x8000 {
+            self.depths[glyph] = Some(0);
+            return Ok(0);
+        }
+
+        // Components follow the header, their arguments and transforms have flag dependent
+        // sizes
+        let mut depth = 0;
+        let mut component = start + 10;
+        loop {
+            let flags = read(component, 2)?;
+            let child = read(component + 2, 2)?;
+            if child >= self.depths.len() {
+                return Err(ill_formed());
+            }
+            depth = depth.max(self.depth(child, level + 1)? + 1);
+
+            let arguments = if flags & 0x01 != 0 { 4 } else { 2 };
+            let transform = match flags {
+                f if f & 0x08 != 0 => 2,
+                f if f & 0x40 != 0 => 4,
+                f if f & 0x80 != 0 => 8,
+                _ => 0,
+            };
+            component += 4 + arguments + transform;
+            if flags & 0x20 == 0 {
+                break;
+            }
+        }
+        self.depths[glyph] = Some(depth);
+        Ok(depth)
     }
-    return result;
 }
 
-/// Rescale from 0..1 to -1..1
-fn rescale(f: f32) -> f32 {
-    -1 as f32 + (f / 1.0 as f32) * 2 as f32
+/// Create the line grid for a font on a screen with the given physical dimensions
+pub(crate) fn font_line_grid(
+    font: &Font,
+    scale: Scale,
+    cell_dimensions: &Vector<f32>,
+    width: u32,
+    height: u32,
+) -> LineGrid {
+    LineGrid::new(
+        &Vector {
+            x: width as f32,
+            y: height as f32,
+        },
+        cell_dimensions,
+        &Vector {
+            x: 0.0,
+            y: font.v_metrics(scale).ascent,
+        },
+    )
+}
+
+/// Create the line grid for a tileset on a screen with the given physical dimensions,
+/// tiles are drawn from the top left corner of the cell
+pub(crate) fn tile_line_grid(cell_dimensions: &Vector<f32>, width: u32, height: u32) -> LineGrid {
+    LineGrid::new(
+        &Vector {
+            x: width as f32,
+            y: height as f32,
+        },
+        cell_dimensions,
+        &Vector { x: 0.0, y: 0.0 },
+    )
+}
+
+/// How a console of a fixed size is scaled to fit the screen
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Scaling {
+    /// Scale cells by whole multiples so every cell has the same size in pixels,
+    /// screens that are too small for the console fall back to fractional scaling
+    Integer,
+    /// Scale cells by any factor so the console fills as much of the screen as possible
+    Fractional,
+}
+
+/// A number of columns and rows that does not change when the screen is resized,
+/// the console is scaled to fit the screen and centred with black bars around it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FixedSize {
+    pub width: u32,
+    pub height: u32,
+    pub scaling: Scaling,
+}
+
+impl FixedSize {
+    /// Console of the given size with integer scaling
+    pub fn new(width: u32, height: u32) -> FixedSize {
+        FixedSize {
+            width,
+            height,
+            scaling: Scaling::Integer,
+        }
+    }
+
+    /// Same size with a different scaling
+    pub fn with_scaling(self, scaling: Scaling) -> FixedSize {
+        FixedSize { scaling, ..self }
+    }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct LineGrid {
     screen_dimensions: Vector<f32>,
     grid_dimensions: Vector<f32>,
     grid_padding: Vector<f32>,
     totals: Vector<u32>,
+    /// Position in pixels of the top left corner of the first cell
+    offset: Vector<f32>,
+    /// Factor the cell dimensions and padding have been multiplied with
+    scale: f32,
 }
 
 impl LineGrid {
@@ -120,100 +382,261 @@ impl LineGrid {
         grid_dimensions: &Vector<f32>,
         grid_padding: &Vector<f32>,
     ) -> LineGrid {
-        let total_y = f32::floor((screen_dimensions.y - grid_padding.y) / grid_dimensions.y);
-        let total_x = f32::floor((screen_dimensions.x - grid_padding.x) / grid_dimensions.x);
+        // The padding is the glyph origin inside a cell, so it takes no room on the screen
+        let total_y = f32::floor(screen_dimensions.y / grid_dimensions.y);
+        let total_x = f32::floor(screen_dimensions.x / grid_dimensions.x);
 
         LineGrid {
-            screen_dimensions: screen_dimensions.clone(),
-            grid_dimensions: grid_dimensions.clone(),
-            grid_padding: grid_padding.clone(),
+            screen_dimensions: *screen_dimensions,
+            grid_dimensions: *grid_dimensions,
+            grid_padding: *grid_padding,
             totals: Vector {
                 x: total_x as u32,
                 y: total_y as u32,
             },
+            offset: Vector { x: 0.0, y: 0.0 },
+            scale: 1.0,
+        }
+    }
+
+    /// The same cells with a fixed number of columns and rows, scaled to fit the screen
+    pub fn fixed(&self, size: &FixedSize) -> LineGrid {
+        let fit = f32::min(
+            self.screen_dimensions.x / (size.width as f32 * self.grid_dimensions.x),
+            self.screen_dimensions.y / (size.height as f32 * self.grid_dimensions.y),
+        );
+        let scale = match size.scaling {
+            Scaling::Integer if fit >= 1.0 => fit.floor(),
+            _ => fit,
+        };
+        self.letterbox(size.width, size.height, scale)
+    }
+
+    /// The same cells with a fixed number of columns and rows at a specific scale,
+    /// centred on the screen
+    pub(crate) fn letterbox(&self, width: u32, height: u32, scale: f32) -> LineGrid {
+        let grid_dimensions = self.grid_dimensions * scale;
+        // Keep the cells on whole pixels
+        let offset = Vector {
+            x: ((self.screen_dimensions.x - width as f32 * grid_dimensions.x) / 2.0).floor(),
+            y: ((self.screen_dimensions.y - height as f32 * grid_dimensions.y) / 2.0).floor(),
+        };
+        LineGrid {
+            screen_dimensions: self.screen_dimensions,
+            grid_dimensions,
+            grid_padding: self.grid_padding * scale,
+            totals: Vector {
+                x: width,
+                y: height,
+            },
+            offset,
+            scale,
+        }
+    }
+
+    /// Size in pixels of the screen the grid is laid out on
+    pub fn screen_dimensions(&self) -> Vector<f32> {
+        self.screen_dimensions
+    }
+
+    /// Number of cells in the x and y direction
+    pub fn totals(&self) -> Vector<u32> {
+        self.totals
+    }
+
+    /// Whether a cell is part of the grid
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x < self.totals.x && y < self.totals.y
+    }
+
+    /// Factor the cells are scaled with to fit a fixed size console on the screen
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Position in pixels of the top left corner of the first cell,
+    /// the size of the bars around a fixed size console
+    pub fn offset(&self) -> Vector<f32> {
+        self.offset
+    }
+
+    /// Area in pixels covered by a specific cell, the padding is the offset of the glyph origin
+    /// inside of this area
+    pub fn cell_rect(&self, x: u32, y: u32) -> Rect<f32> {
+        let min = point(
+            x as f32 * self.grid_dimensions.x + self.offset.x,
+            y as f32 * self.grid_dimensions.y + self.offset.y,
+        );
+        Rect {
+            min,
+            max: min + self.grid_dimensions,
+        }
+    }
+
+    /// Cell that covers a position in physical pixels, None outside of the grid like in the
+    /// bars around a fixed size console. The padding is the glyph origin inside a cell,
+    /// so it does not move the cells
+    pub fn cell_at(&self, pixel: Vector<f32>) -> Option<(u32, u32)> {
+        let x = ((pixel.x - self.offset.x) / self.grid_dimensions.x).floor();
+        let y = ((pixel.y - self.offset.y) / self.grid_dimensions.y).floor();
+        // Also rejects positions in grids without cell dimensions
+        if !(x >= 0.0 && y >= 0.0) {
+            return None;
+        }
+        let (x, y) = (x as u32, y as u32);
+        if self.contains(x, y) {
+            Some((x, y))
+        } else {
+            None
         }
     }
 
     /// Retrieve coordinates for a specific index
     pub fn coordinates_for(&self, index: u32) -> Vector<f32> {
-        let x = index % self.totals.x;
-        let y = index / self.totals.x;
+        self.cell_coordinates(index % self.totals.x, index / self.totals.x)
+    }
 
+    /// Retrieve coordinates for a specific cell
+    pub fn cell_coordinates(&self, x: u32, y: u32) -> Vector<f32> {
         Vector {
-            x: x as f32 * self.grid_dimensions.x + self.grid_padding.x,
-            y: y as f32 * self.grid_dimensions.y + self.grid_padding.y,
+            x: x as f32 * self.grid_dimensions.x + self.grid_padding.x + self.offset.x,
+            y: y as f32 * self.grid_dimensions.y + self.grid_padding.y + self.offset.y,
         }
     }
 }
 
-/// Create a grid, lines for easy display
-pub(crate) fn create_grid(
-    grid_size: &Vector<f32>,
-    grid_padding: &Vector<f32>,
-    display: &glium::Display,
-) -> (
-    LineGrid,
-    glium::VertexBuffer<Vertex>,
-    glium::IndexBuffer<u16>,
-) {
-    let mut vertices: Vec<Vertex> = Vec::new();
+/// Lines drawn between the cells of the grid, useful to debug the layout of a console
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GridOverlay {
+    /// Colour of the lines, the alpha component blends them with the cells below
+    pub colour: Colour,
+    /// Number of cells between two lines
+    pub spacing: u32,
+}
 
-    // Get the screen dimensions
-    let (screen_width, screen_height) = {
-        let (w, h) = display.get_framebuffer_dimensions();
-        (w as f32, h as f32)
-    };
+impl GridOverlay {
+    /// Lines drawn across a number of cells, every Nth line and the closing border as the
+    /// index of the cell they are in front of
+    #[cfg_attr(not(feature = "opengl"), allow(dead_code))]
+    pub(crate) fn lines(&self, cells: u32) -> Vec<u32> {
+        let mut lines: Vec<u32> = (0..=cells).step_by(self.spacing.max(1) as usize).collect();
+        if lines.last() != Some(&cells) {
+            lines.push(cells);
+        }
+        lines
+    }
+}
 
-    // Create the rogue grid for grid calculations
-    let rogue_grid = LineGrid::new(
-        &Vector {
-            x: screen_width,
-            y: screen_height,
-        },
-        grid_size,
-        grid_padding,
-    );
-
-    // Create vertex at ends of screen to create grid cells
-    for y in 0..((screen_height - grid_padding.y) / grid_size.y) as u32 {
-        vertices.push(Vertex {
-            position: [
-                -1.0,
-                -rescale((y as f32 * grid_size.y + grid_padding.y) / screen_height),
-            ],
-            color: [1.0, 1.0, 1.0],
-        });
-        vertices.push(Vertex {
-            position: [
-                1.0,
-                -rescale((y as f32 * grid_size.y + grid_padding.y) / screen_height),
-            ],
-            color: [1.0, 1.0, 1.0],
-        });
-    }
-
-    // Create vertex at ends of screen to create the grid cells
-    for x in 0..((screen_width - grid_padding.x) / grid_size.x) as u32 {
-        vertices.push(Vertex {
-            position: [
-                rescale((x as f32 * grid_size.x + grid_padding.x) / screen_width),
-                -1.0,
-            ],
-            color: [1.0, 1.0, 1.0],
-        });
-        vertices.push(Vertex {
-            position: [
-                rescale((x as f32 * grid_size.x + grid_padding.x) / screen_width),
-                1.0,
-            ],
-            color: [1.0, 1.0, 1.0],
-        });
-    }
-
-    let vb = glium::VertexBuffer::new(display, &vertices).unwrap();
-    let indices: Vec<u16> = (0..screen_height as u16).collect();
-    let ib =
-        glium::IndexBuffer::new(display, glium::index::PrimitiveType::LinesList, &indices).unwrap();
-
-    return (rogue_grid, vb, ib);
+impl Default for GridOverlay {
+    fn default() -> GridOverlay {
+        GridOverlay {
+            colour: Colour::WHITE,
+            spacing: 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grid of 10 by 20 pixel cells with the glyph origin 16 pixels down the cell
+    fn grid(width: f32, height: f32) -> LineGrid {
+        LineGrid::new(
+            &Vector {
+                x: width,
+                y: height,
+            },
+            &Vector { x: 10.0, y: 20.0 },
+            &Vector { x: 0.0, y: 16.0 },
+        )
+    }
+
+    #[test]
+    fn grid_lines_close_the_grid() {
+        let overlay = |spacing| GridOverlay {
+            spacing,
+            ..GridOverlay::default()
+        };
+        assert_eq!(overlay(1).lines(3), vec![0, 1, 2, 3]);
+        assert_eq!(overlay(0).lines(2), vec![0, 1, 2]);
+        assert_eq!(overlay(2).lines(4), vec![0, 2, 4]);
+        assert_eq!(overlay(3).lines(7), vec![0, 3, 6, 7]);
+        assert_eq!(overlay(10).lines(4), vec![0, 4]);
+        assert_eq!(overlay(2).lines(0), vec![0]);
+    }
+
+    #[test]
+    fn glyphs_are_snapped_to_whole_pixels() {
+        let font = Font::from_bytes(&include_bytes!("../fonts/consola.ttf")[..]).unwrap();
+        let grid = LineGrid::new(
+            &Vector { x: 100.0, y: 100.0 },
+            &Vector { x: 9.3, y: 17.6 },
+            &Vector { x: 0.0, y: 13.4 },
+        );
+        let cell = Cell {
+            glyph: 'a',
+            ..Cell::default()
+        };
+        for &(x, y) in &[(0, 0), (1, 0), (3, 2), (7, 4)] {
+            let glyph = layout_cell(&font, Scale::uniform(16.0), &grid, x, y, &cell);
+            let position = glyph.position();
+            assert_eq!(position.x.fract(), 0.0);
+            assert_eq!(position.y.fract(), 0.0);
+        }
+    }
+
+    #[test]
+    fn padding_takes_no_room() {
+        assert_eq!(grid(100.0, 30.0).totals(), Vector { x: 10, y: 1 });
+        assert_eq!(grid(99.0, 40.0).totals(), Vector { x: 9, y: 2 });
+        assert_eq!(grid(9.0, 19.0).totals(), Vector { x: 0, y: 0 });
+    }
+
+    #[test]
+    fn fixed_size_is_scaled_and_centred() {
+        let size = FixedSize::new(10, 3);
+        let fixed = grid(350.0, 130.0).fixed(&size);
+        assert_eq!(fixed.totals(), Vector { x: 10, y: 3 });
+        assert_eq!(fixed.scale(), 2.0);
+        assert_eq!(fixed.offset(), Vector { x: 75.0, y: 5.0 });
+        assert_eq!(fixed.cell_rect(9, 2).max, point(275.0, 125.0));
+
+        let fractional = grid(350.0, 130.0).fixed(&size.with_scaling(Scaling::Fractional));
+        assert_eq!(fractional.scale(), 130.0 / 60.0);
+        assert_eq!(fractional.offset(), Vector { x: 66.0, y: 0.0 });
+
+        // Screens that are too small for whole multiples shrink the cells
+        let small = grid(50.0, 60.0).fixed(&size);
+        assert_eq!(small.scale(), 0.5);
+        assert_eq!(small.offset(), Vector { x: 0.0, y: 15.0 });
+    }
+
+    #[test]
+    fn cell_at_pixel_positions() {
+        let grid = grid(100.0, 40.0);
+        assert_eq!(grid.cell_at(Vector { x: 0.0, y: 0.0 }), Some((0, 0)));
+        // The glyph origin 16 pixels down the cell does not move the cells
+        assert_eq!(grid.cell_at(Vector { x: 15.0, y: 19.9 }), Some((1, 0)));
+        assert_eq!(grid.cell_at(Vector { x: 99.9, y: 39.9 }), Some((9, 1)));
+        assert_eq!(grid.cell_at(Vector { x: 100.0, y: 0.0 }), None);
+        assert_eq!(grid.cell_at(Vector { x: -0.1, y: 5.0 }), None);
+        assert_eq!(
+            grid.cell_at(Vector {
+                x: f32::NAN,
+                y: 5.0
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn cell_at_skips_the_bars_of_fixed_sizes() {
+        let fixed = grid(350.0, 130.0).fixed(&FixedSize::new(10, 3));
+        assert_eq!(fixed.cell_at(Vector { x: 75.0, y: 5.0 }), Some((0, 0)));
+        assert_eq!(fixed.cell_at(Vector { x: 274.9, y: 124.9 }), Some((9, 2)));
+        assert_eq!(fixed.cell_at(Vector { x: 74.9, y: 5.0 }), None);
+        assert_eq!(fixed.cell_at(Vector { x: 275.0, y: 5.0 }), None);
+        assert_eq!(fixed.cell_at(Vector { x: 100.0, y: 4.9 }), None);
+    }
 }
@@ -1,9 +1,15 @@
+use crate::atlas::GlyphAtlas;
+use crate::colour::Colour;
+use crate::console::Cell;
+use crate::tileset::Tileset;
+use crate::util::LineGrid;
 use glium::implement_vertex;
+use rusttype::{point, PositionedGlyph, Rect};
 
 #[derive(Copy, Clone)]
 pub struct Vertex {
     pub position: [f32; 2],
-    pub color: [f32; 3],
+    pub color: [f32; 4],
 }
 
 implement_vertex!(Vertex, position, color);
@@ -16,3 +22,204 @@ pub struct TextVertex {
 }
 
 implement_vertex!(TextVertex, position, tex_coords, colour);
+
+#[derive(Copy, Clone)]
+pub struct BackgroundVertex {
+    pub position: [f32; 2],
+    pub colour: [f32; 4],
+}
+
+implement_vertex!(BackgroundVertex, position, colour);
+
+/// Corner of the quad that is shared by every cell, from 0,0 to 1,1
+#[derive(Copy, Clone)]
+pub struct QuadVertex {
+    pub corner: [f32; 2],
+}
+
+implement_vertex!(QuadVertex, corner);
+
+/// Everything needed to draw a single cell with instancing, rectangles are min x, min y,
+/// max x, max y and positions are in -1..1
+#[derive(Copy, Clone)]
+pub struct CellInstance {
+    pub cell_rect: [f32; 4],
+    pub glyph_rect: [f32; 4],
+    pub uv_rect: [f32; 4],
+    pub fg: [f32; 4],
+    pub bg: [f32; 4],
+}
+
+implement_vertex!(CellInstance, cell_rect, glyph_rect, uv_rect, fg, bg);
+
+/// Vertices of a cell without a glyph, a quad without area is not rasterized
+const EMPTY_TEXT_QUAD: [TextVertex; 6] = [TextVertex {
+    position: [0.0, 0.0],
+    tex_coords: [0.0, 0.0],
+    colour: [0.0, 0.0, 0.0, 0.0],
+}; 6];
+
+/// Vertices of a cell that is not drawn
+pub(crate) const EMPTY_BACKGROUND_QUAD: [BackgroundVertex; 6] = [BackgroundVertex {
+    position: [0.0, 0.0],
+    colour: [0.0, 0.0, 0.0, 0.0],
+}; 6];
+
+/// Scale a rectangle in pixels to -1..1
+pub(crate) fn gl_rect(grid: &LineGrid, rect: Rect<f32>) -> Rect<f32> {
+    let screen = grid.screen_dimensions();
+    Rect {
+        min: point(
+            rescale(rect.min.x / screen.x),
+            -rescale(rect.min.y / screen.y),
+        ),
+        max: point(
+            rescale(rect.max.x / screen.x),
+            -rescale(rect.max.y / screen.y),
+        ),
+    }
+}
+
+/// Area of the screen in -1..1 and the area of the font texture that is drawn onto it
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct TexturedRect {
+    pub gl_rect: Rect<f32>,
+    pub uv_rect: Rect<f32>,
+}
+
+/// Textured rectangle for a glyph that has been cached, nothing when it is not cached
+pub(crate) fn glyph_rect(
+    grid: &LineGrid,
+    glyph: &PositionedGlyph,
+    atlas: &GlyphAtlas,
+) -> Option<TexturedRect> {
+    match atlas.rect_for(glyph) {
+        Some((uv_rect, screen_rect)) => {
+            let rect = Rect {
+                min: point(screen_rect.min.x as f32, screen_rect.min.y as f32),
+                max: point(screen_rect.max.x as f32, screen_rect.max.y as f32),
+            };
+            Some(TexturedRect {
+                gl_rect: gl_rect(grid, rect),
+                uv_rect,
+            })
+        }
+        None => None,
+    }
+}
+
+/// Textured rectangle for the tile of a cell, nothing when the tileset has no tile
+pub(crate) fn tile_rect(
+    grid: &LineGrid,
+    x: u32,
+    y: u32,
+    cell: &Cell,
+    tileset: &Tileset,
+) -> Option<TexturedRect> {
+    let tile = tileset.tile_for(cell.glyph)?;
+
+    let (sheet_width, sheet_height) = tileset.dimensions();
+    let (tile_x, tile_y) = tileset.tile_origin(tile);
+    let uv_rect = Rect {
+        min: point(
+            tile_x as f32 / sheet_width as f32,
+            tile_y as f32 / sheet_height as f32,
+        ),
+        max: point(
+            (tile_x + tileset.tile_width()) as f32 / sheet_width as f32,
+            (tile_y + tileset.tile_height()) as f32 / sheet_height as f32,
+        ),
+    };
+
+    Some(TexturedRect {
+        gl_rect: gl_rect(grid, grid.cell_rect(x, y)),
+        uv_rect,
+    })
+}
+
+/// Two triangles that map a texture rectangle onto a rectangle of the screen,
+/// an empty quad without a rectangle
+pub(crate) fn text_quad(rect: Option<TexturedRect>, colour: Colour) -> [TextVertex; 6] {
+    let TexturedRect { gl_rect, uv_rect } = match rect {
+        Some(rect) => rect,
+        None => return EMPTY_TEXT_QUAD,
+    };
+    let colour = colour.into();
+    // Associate vertices with texture coords
+    [
+        TextVertex {
+            position: [gl_rect.min.x, gl_rect.max.y],
+            tex_coords: [uv_rect.min.x, uv_rect.max.y],
+            colour,
+        },
+        TextVertex {
+            position: [gl_rect.min.x, gl_rect.min.y],
+            tex_coords: [uv_rect.min.x, uv_rect.min.y],
+            colour,
+        },
+        TextVertex {
+            position: [gl_rect.max.x, gl_rect.min.y],
+            tex_coords: [uv_rect.max.x, uv_rect.min.y],
+            colour,
+        },
+        TextVertex {
+            position: [gl_rect.max.x, gl_rect.min.y],
+            tex_coords: [uv_rect.max.x, uv_rect.min.y],
+            colour,
+        },
+        TextVertex {
+            position: [gl_rect.max.x, gl_rect.max.y],
+            tex_coords: [uv_rect.max.x, uv_rect.max.y],
+            colour,
+        },
+        TextVertex {
+            position: [gl_rect.min.x, gl_rect.max.y],
+            tex_coords: [uv_rect.min.x, uv_rect.max.y],
+            colour,
+        },
+    ]
+}
+
+/// Coloured rectangle for the background of a cell
+pub(crate) fn background_quad(
+    grid: &LineGrid,
+    x: u32,
+    y: u32,
+    cell: &Cell,
+) -> [BackgroundVertex; 6] {
+    let colour = cell.bg.into();
+    let rect = gl_rect(grid, grid.cell_rect(x, y));
+    let (min, max) = (rect.min, rect.max);
+
+    [
+        BackgroundVertex {
+            position: [min.x, max.y],
+            colour,
+        },
+        BackgroundVertex {
+            position: [min.x, min.y],
+            colour,
+        },
+        BackgroundVertex {
+            position: [max.x, min.y],
+            colour,
+        },
+        BackgroundVertex {
+            position: [max.x, min.y],
+            colour,
+        },
+        BackgroundVertex {
+            position: [max.x, max.y],
+            colour,
+        },
+        BackgroundVertex {
+            position: [min.x, max.y],
+            colour,
+        },
+    ]
+}
+
+/// Rescale from 0..1 to -1..1
+pub(crate) fn rescale(f: f32) -> f32 {
+    -1.0 + f * 2.0
+}